@@ -0,0 +1,127 @@
+use crate::{
+    base::{Operator, Room, Save, ShiftCount},
+    db::{Database, DbResult},
+    gamedata::{FacilityKind, GameData, Tier},
+    roster::get_resolved_save,
+};
+use ahash::HashMap;
+use serde::Serialize;
+use tauri::State;
+
+/// Layout problems that can be found without simulating production.
+/// Output bottlenecks (full storage, missing inputs) depend on simulated item counts
+/// and are not reported here.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostics {
+    idle_slots: Box<[IdleSlots]>,
+    ineffective_chars: Box<[IneffectiveChar]>,
+}
+
+// Empty slots of a room during one shift
+#[derive(Serialize)]
+struct IdleSlots {
+    room: Room,
+    shift: ShiftCount,
+    count: usize,
+    minutes: u32, // Total idle time across all empty slots
+}
+
+// An operator with no base skill that takes effect in the room they are assigned to
+#[derive(Serialize)]
+struct IneffectiveChar {
+    room: Room,
+    char: Operator,
+    shifts: Vec<ShiftCount>,
+}
+
+impl Diagnostics {
+    // The save's roster should be resolved with `get_resolved_save`
+    fn from_save(save: &Save, data: &GameData) -> Self {
+        let tiers: HashMap<&str, Tier> = save
+            .chars
+            .iter()
+            .map(|c| (c.char.as_ref(), c.tier))
+            .collect();
+
+        let mut idle_slots = Vec::new();
+        let mut ineffective_chars: Vec<IneffectiveChar> = Vec::new();
+
+        for room in save.layout.staffed_rooms() {
+            let capacity = data.capacity(room.kind(), room.level);
+
+            for shift in 0..save.max_shift {
+                let mut count = 0;
+
+                for slot in room.slots(shift, capacity) {
+                    let Some(char) = slot else {
+                        count += 1;
+                        continue;
+                    };
+
+                    // Operators rest in dormitories regardless of their skills
+                    if room.kind() == FacilityKind::Dormitory {
+                        continue;
+                    }
+
+                    let Some(op) = data.operator(char) else {
+                        continue;
+                    };
+
+                    // Operators missing from the roster are assumed to have every skill unlocked
+                    let tier = tiers.get(char).copied().unwrap_or(Tier::MAX);
+
+                    if op
+                        .skills_at(tier)
+                        .any(|skill| skill.facility() == Some(room.kind()))
+                    {
+                        continue;
+                    }
+
+                    match ineffective_chars
+                        .iter_mut()
+                        .find(|entry| entry.room == room.room && entry.char.as_ref() == char)
+                    {
+                        Some(entry) => entry.shifts.push(shift),
+                        None => ineffective_chars.push(IneffectiveChar {
+                            room: room.room,
+                            char: char.into(),
+                            shifts: vec![shift],
+                        }),
+                    }
+                }
+
+                if count > 0 {
+                    idle_slots.push(IdleSlots {
+                        room: room.room,
+                        shift,
+                        count,
+                        minutes: u32::from(save.shift_duration(shift))
+                            * u32::try_from(count).expect("Failed to convert slot count to u32"),
+                    });
+                }
+            }
+        }
+
+        Self {
+            idle_slots: idle_slots.into_boxed_slice(),
+            ineffective_chars: ineffective_chars.into_boxed_slice(),
+        }
+    }
+}
+
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn analyze_save(
+    db: State<'_, Database>,
+    data: State<'_, GameData>,
+    name: String,
+) -> DbResult<Diagnostics> {
+    let save = db.run(move |conn| get_resolved_save(&*conn, &name)).await?;
+
+    Ok(Diagnostics::from_save(&save, &data))
+}
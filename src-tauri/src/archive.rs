@@ -0,0 +1,119 @@
+use crate::{
+    db::{
+        get_tags, map_fetch_error, select_filtered, write_file, Database, DbError, DbResult,
+        NamedSave, OperationId, SaveFilter,
+    },
+    roster::{get_shared_roster, resolve_roster},
+    schema::SCHEMA_VERSION,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{borrow::Cow, path::PathBuf};
+use tauri::State;
+
+const ARCHIVE_VERSION: u32 = 1; // Version of the archive format, raised when it changes
+
+/// Describes the contents of a save archive
+#[derive(Serialize)]
+struct Manifest {
+    version: u32,
+    app_version: &'static str,
+    schema_version: usize, // Schema version of the database that the saves were exported from
+    exported: Box<str>,
+    saves: Vec<ManifestEntry>,
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+    name: Box<str>,
+    created: Box<str>,
+    modified: Box<str>,
+    description: Box<str>,
+    folder: Option<Box<str>>,
+    tags: Box<[Box<str>]>,
+}
+
+#[derive(Serialize)]
+struct Archive {
+    manifest: Manifest,
+    saves: Vec<NamedSave<'static>>,
+}
+
+fn format_time(time: DateTime<Utc>) -> Box<str> {
+    time.to_rfc3339().into_boxed_str()
+}
+
+/// Writes every save matching the given filter, or all saves if no filter is given,
+/// into a single archive at `path`, overwriting any existing file.
+/// Each save in the archive is in the format written by `export_save`.
+/// Returns the number of saves in the archive.
+/// The export can be cancelled with `cancel_operation`, using `operation` as its ID.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - The operation was cancelled
+/// - The archive cannot be written
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn export_all(
+    db: State<'_, Database>,
+    path: PathBuf,
+    filter: Option<SaveFilter>,
+    operation: OperationId,
+) -> DbResult<usize> {
+    let filter = filter.unwrap_or_default();
+
+    let archive = db
+        .run_cancellable(operation, move |conn, cancelled| {
+            let mut manifest = Manifest {
+                version: ARCHIVE_VERSION,
+                app_version: env!("CARGO_PKG_VERSION"),
+                schema_version: SCHEMA_VERSION,
+                exported: format_time(Utc::now()),
+                saves: Vec::new(),
+            };
+            let mut saves = Vec::new();
+
+            // Saves are read in one transaction, so the archive is a consistent snapshot
+            let tx = conn.transaction().map_err(DbError::Execution)?;
+            let roster = get_shared_roster(&tx)?;
+            let mut query = tx
+                .prepare_cached(select_filtered!(", s.data"))
+                .map_err(DbError::Execution)?;
+            let mut rows = query.query(filter.params()).map_err(DbError::Fetching)?;
+
+            while let Some(row) = rows.next().map_err(DbError::Fetching)? {
+                cancelled.check()?;
+
+                let name: Box<str> = row.get("name").map_err(DbError::Fetching)?;
+                let created: DateTime<Utc> = row.get("created").map_err(DbError::Fetching)?;
+                let modified: DateTime<Utc> =
+                    row.get("last_modified").map_err(DbError::Fetching)?;
+
+                manifest.saves.push(ManifestEntry {
+                    name: name.clone(),
+                    created: format_time(created),
+                    modified: format_time(modified),
+                    description: row.get("description").map_err(DbError::Fetching)?,
+                    folder: row.get("folder").map_err(DbError::Fetching)?,
+                    tags: get_tags(row).map_err(DbError::Fetching)?,
+                });
+                let mut data = row.get("data").map_err(map_fetch_error)?;
+                resolve_roster(&mut data, &roster);
+                saves.push(NamedSave {
+                    name: Cow::Owned(name.into()),
+                    data,
+                });
+            }
+
+            Ok(Archive { manifest, saves })
+        })
+        .await?;
+
+    let contents = serde_json::to_vec(&archive).map_err(|e| DbError::Export(e.into()))?;
+    write_file(path, contents).await?;
+
+    Ok(archive.saves.len())
+}
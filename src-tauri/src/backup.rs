@@ -0,0 +1,126 @@
+use crate::{
+    db::{Database, DbError, DbResult, OperationId},
+    health::{self, Health, HealthReport},
+    history::History,
+    schema::{self, get_schema_version, has_save_table, migrate, SCHEMA_VERSION},
+    search,
+};
+use rusqlite::{
+    backup::{Backup, StepResult},
+    Connection, OpenFlags,
+};
+use std::{path::PathBuf, thread, time::Duration};
+use tauri::State;
+
+const BACKUP_STEP_PAGES: i32 = 100; // Number of pages copied between cancellation checks
+const BACKUP_RETRY_DELAY: Duration = Duration::from_millis(10); // Delay before retrying a busy step
+
+/// Writes a consistent copy of the database to `path`, overwriting any existing file.
+/// The backup can be cancelled with `cancel_operation`, using `operation` as its ID,
+/// in which case the file is left as it was.
+///
+/// # Errors
+/// Returns error if:
+/// - The backup cannot be written
+/// - The operation was cancelled
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn backup_database(
+    db: State<'_, Database>,
+    path: PathBuf,
+    operation: OperationId,
+) -> DbResult<()> {
+    db.run_cancellable(operation, move |conn, cancelled| {
+        let mut target = Connection::open(path).map_err(DbError::Backup)?;
+        let backup = Backup::new(conn, &mut target).map_err(DbError::Backup)?;
+
+        // The database is copied a few pages at a time, so that the backup can be cancelled
+        loop {
+            cancelled.check()?;
+
+            match backup.step(BACKUP_STEP_PAGES).map_err(DbError::Backup)? {
+                StepResult::Done => return Ok(()),
+                StepResult::More => {}
+                _ => thread::sleep(BACKUP_RETRY_DELAY),
+            }
+        }
+    })
+    .await
+}
+
+/// Replaces the database with a backup written by `backup_database`.
+/// The backup is checked and upgraded to the current schema before anything is replaced,
+/// so a failed restore leaves the database untouched. The replaced database is kept as
+/// a copy next to the database file, and the restored database is health-checked like at
+/// startup. Returns the report of the health check.
+///
+/// # Errors
+/// Returns error if:
+/// - The file does not exist, is not a database backup, or is corrupted
+/// - The backup has a newer schema version than this app supports
+/// - The backup cannot be upgraded to the current schema
+/// - The current database cannot be copied
+/// - The database cannot be replaced or reindexed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn restore_database(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    health: State<'_, Health>,
+    path: PathBuf,
+) -> DbResult<HealthReport> {
+    let report = db
+        .run(move |conn| {
+            // The backup is opened read-only, so that a missing file is not created as an empty
+            // database and restored over the current one
+            let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|e| DbError::InvalidBackup(Some(e)))?;
+            let mut staged =
+                Connection::open_in_memory().map_err(|e| DbError::BackupRestore(e.into()))?;
+            Backup::new(&source, &mut staged)
+                .and_then(|backup| {
+                    backup.run_to_completion(BACKUP_STEP_PAGES, Duration::ZERO, None)
+                })
+                .map_err(|e| DbError::InvalidBackup(Some(e)))?;
+            drop(source);
+
+            let integrity: String = staged
+                .pragma_query_value(None, "integrity_check", |row| row.get(0))
+                .map_err(|e| DbError::InvalidBackup(Some(e)))?;
+            if integrity != "ok" {
+                return Err(DbError::InvalidBackup(None));
+            }
+
+            let version =
+                get_schema_version(&staged).map_err(|e| DbError::InvalidBackup(Some(e)))?;
+            if version > SCHEMA_VERSION {
+                return Err(DbError::NewerBackup);
+            }
+            // Backups written by the app always have a schema, unlike other SQLite databases
+            let has_saves = has_save_table(&staged).map_err(|e| DbError::InvalidBackup(Some(e)))?;
+            if version == 0 || !has_saves {
+                return Err(DbError::InvalidBackup(None));
+            }
+
+            migrate(&mut staged).map_err(DbError::BackupRestore)?;
+
+            schema::backup(conn, "pre-restore").map_err(DbError::BackupRestore)?;
+            Backup::new(&staged, conn)
+                .and_then(|backup| {
+                    backup.run_to_completion(BACKUP_STEP_PAGES, Duration::ZERO, None)
+                })
+                .map_err(|e| DbError::BackupRestore(e.into()))?;
+
+            // The fact index and health report have to match the restored saves
+            search::reindex(conn).map_err(|e| DbError::BackupRestore(e.into()))?;
+            let integrity = health::repair(conn);
+            Ok(health::check(conn, integrity))
+        })
+        .await?;
+
+    // Edits made before the restore cannot be undone on top of the restored saves
+    history.clear();
+    health.set(report.clone());
+
+    Ok(report)
+}
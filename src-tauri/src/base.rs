@@ -1,31 +1,66 @@
-use crate::MAX_SAVE_SIZE;
+use crate::{gamedata::FacilityKind, MAX_SAVE_SIZE};
 use bincode::{
     config::{standard, Configuration, Limit, LittleEndian, Varint},
-    decode_from_slice, encode_to_vec, Decode, Encode,
+    decode_from_slice, encode_to_vec,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
 };
+use chrono::DateTime;
 use rusqlite::{
     types::{FromSql, FromSqlError, ToSql, ToSqlOutput, Value, ValueRef},
     Error as SqlError,
 };
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 type DroneCount = u32;
-type ShiftCount = u16;
+pub(crate) type ShiftCount = u16;
+pub(crate) type Minutes = u16;
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+pub(crate) const MAX_SHIFT_DURATION: Minutes = 9999;
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
 pub struct Save {
-    layout: Layout,
-    chars: Box<[CharData]>,
+    pub(crate) layout: Layout,
+    pub(crate) chars: Box<[CharData]>,
     drones: DroneCount, // Drone capacity; drones will regenerate up to this amount
-    max_shift: ShiftCount, // Total number of shifts in rotation
-    interval: u16,      // Duration of one shift (in minutes)
+    pub(crate) max_shift: ShiftCount, // Total number of shifts in rotation
+    interval: Minutes,  // Duration of one shift
+    #[serde(default)]
+    durations: Box<[Minutes]>, // Duration of each shift; if empty, every shift lasts `interval`
+    #[serde(default)]
+    pub(crate) clock: Option<Clock>, // Real time at which the rotation starts
 }
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
-struct Layout {
+pub(crate) struct Clock {
+    pub(crate) start: i64, // Unix timestamp (in seconds) of the start of the first shift
+    pub(crate) server: Server,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Server {
+    US,
+    CN,
+}
+
+impl Server {
+    /// Returns the offset of server time from UTC (in seconds).
+    pub(crate) const fn utc_offset(self) -> i64 {
+        match self {
+            Self::US => -7 * 3600,
+            Self::CN => 8 * 3600,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Layout {
     cc: Facility,
     tp: Box<[TradingPost]>,
     fac: Box<[Factory]>,
@@ -39,16 +74,16 @@ struct Layout {
 
 type FacilityLevel = u8;
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
 struct NoShiftFacility {
     level: FacilityLevel,
 }
 
-type Operator = Box<str>;
+pub(crate) type Operator = Box<str>;
 type Shifts = Box<[Option<Operator>]>;
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
 struct Facility {
     level: FacilityLevel,
@@ -57,7 +92,7 @@ struct Facility {
 
 type Boosts = Box<[Option<DroneCount>]>;
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
 struct TradingPost {
     level: FacilityLevel,
@@ -66,7 +101,7 @@ struct TradingPost {
     products: Box<[Option<TradingProduct>]>,
 }
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
 struct Factory {
     level: FacilityLevel,
@@ -75,14 +110,14 @@ struct Factory {
     products: Box<[Option<FactoryProduct>]>,
 }
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(rename_all = "lowercase")]
 enum TradingProduct {
     Lmd,     // consumes Pure Gold, produces LMD
     Orundum, // consumes Originium Shard, produces Orundum
 }
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(rename_all = "lowercase")]
 enum FactoryProduct {
     Exp200,  // Drill Battle Record
@@ -92,11 +127,61 @@ enum FactoryProduct {
     Shard,   // Originium Shard
 }
 
-#[derive(Serialize, Deserialize, Encode, Decode)]
+impl TradingProduct {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Lmd => "lmd",
+            Self::Orundum => "orundum",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lmd" => Some(Self::Lmd),
+            "orundum" => Some(Self::Orundum),
+            _ => None,
+        }
+    }
+}
+
+impl FactoryProduct {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Exp200 => "exp200",
+            Self::Exp400 => "exp400",
+            Self::Exp1000 => "exp1000",
+            Self::Gold => "gold",
+            Self::Shard => "shard",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "exp200" => Some(Self::Exp200),
+            "exp400" => Some(Self::Exp400),
+            "exp1000" => Some(Self::Exp1000),
+            "gold" => Some(Self::Gold),
+            "shard" => Some(Self::Shard),
+            _ => None,
+        }
+    }
+}
+
+// Sets an item of a list, extending the list with default items if it is too short.
+fn set_at<T: Default>(items: &mut Box<[T]>, index: usize, value: T) {
+    let mut list = std::mem::take(items).into_vec();
+    if list.len() <= index {
+        list.resize_with(index + 1, T::default);
+    }
+    list[index] = value;
+    *items = list.into_boxed_slice();
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Encode, Decode)]
 #[serde(deny_unknown_fields)]
-struct CharData {
-    char: Operator,
-    tier: u8,
+pub(crate) struct CharData {
+    pub(crate) char: Operator,
+    pub(crate) tier: u8,
 }
 
 impl NoShiftFacility {
@@ -136,6 +221,258 @@ impl Factory {
     }
 }
 
+/// Identifies a single room within a layout
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) struct Room {
+    kind: FacilityKind,
+    index: usize,
+}
+
+// Products made by a room during each shift, for rooms that make products
+#[derive(Clone, Copy)]
+enum Products<'a> {
+    None,
+    Trading(&'a [Option<TradingProduct>]),
+    Factory(&'a [Option<FactoryProduct>]),
+}
+
+/// A room that operators can be assigned to, along with its assignments
+pub(crate) struct StaffedRoom<'a> {
+    pub(crate) room: Room,
+    pub(crate) level: FacilityLevel,
+    shifts: &'a Shifts,
+    boosts: &'a [Option<DroneCount>], // Drones used during each shift, for rooms that make products
+    products: Products<'a>,
+}
+
+impl<'a> StaffedRoom<'a> {
+    pub(crate) fn kind(&self) -> FacilityKind {
+        self.room.kind
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.room.index
+    }
+
+    /// Iterates over the slots of one shift. Assignments are stored shift by shift,
+    /// with `capacity` slots per shift; missing trailing entries count as empty slots.
+    pub(crate) fn slots(
+        &self,
+        shift: ShiftCount,
+        capacity: usize,
+    ) -> impl Iterator<Item = Option<&str>> {
+        let start = usize::from(shift) * capacity;
+
+        (start..start + capacity).map(|i| self.shifts.get(i).and_then(Option::as_deref))
+    }
+
+    /// Iterates over every operator assigned to the room, across all shifts.
+    pub(crate) fn chars(&self) -> impl Iterator<Item = &'a str> {
+        self.shifts.iter().flatten().map(|char| &**char)
+    }
+
+    /// Returns the names of the products made by the room, across all shifts.
+    pub(crate) fn products(&self) -> Vec<&'static str> {
+        match self.products {
+            Products::None => Vec::new(),
+            Products::Trading(products) => products
+                .iter()
+                .flatten()
+                .map(|product| product.name())
+                .collect(),
+            Products::Factory(products) => products
+                .iter()
+                .flatten()
+                .map(|product| product.name())
+                .collect(),
+        }
+    }
+
+    /// Returns whether drones are used to speed up the room during a shift.
+    pub(crate) fn is_boosted(&self, shift: ShiftCount) -> bool {
+        matches!(self.boosts.get(usize::from(shift)), Some(Some(drones)) if *drones > 0)
+    }
+
+    /// Returns the name of the product made during a shift, if any.
+    pub(crate) fn product(&self, shift: ShiftCount) -> Option<&'static str> {
+        let shift = usize::from(shift);
+
+        match self.products {
+            Products::None => None,
+            Products::Trading(products) => products
+                .get(shift)
+                .copied()
+                .flatten()
+                .map(TradingProduct::name),
+            Products::Factory(products) => products
+                .get(shift)
+                .copied()
+                .flatten()
+                .map(FactoryProduct::name),
+        }
+    }
+}
+
+/// Number of rooms on the left side of the base, each of which can be built as a trading post,
+/// factory or power plant
+pub(crate) const MAX_LEFT_ROOMS: usize = 9;
+
+impl Layout {
+    /// Creates a fully-upgraded layout with the given numbers of trading posts, factories
+    /// and power plants (e.g. 2, 4 and 3 for a 2-4-3 layout).
+    /// Rooms beyond `MAX_LEFT_ROOMS` are left out, starting with power plants.
+    pub(crate) fn upgraded(tp: usize, fac: usize, pp: usize) -> Self {
+        let tp = tp.min(MAX_LEFT_ROOMS);
+        let fac = fac.min(MAX_LEFT_ROOMS - tp);
+        let pp = pp.min(MAX_LEFT_ROOMS - tp - fac);
+
+        Self {
+            cc: Facility::new(5),
+            tp: (0..tp).map(|_| TradingPost::new(3)).collect(),
+            fac: (0..fac).map(|_| Factory::new(3)).collect(),
+            pp: (0..pp).map(|_| Facility::new(3)).collect(),
+            workshop: NoShiftFacility::new(3),
+            rr: Facility::new(3),
+            office: Facility::new(3),
+            train: NoShiftFacility::new(3),
+            dorm: [
+                Facility::new(5),
+                Facility::new(5),
+                Facility::new(5),
+                Facility::new(5),
+            ],
+        }
+    }
+
+    /// Returns every built room that operators can be assigned to.
+    pub(crate) fn staffed_rooms(&self) -> Vec<StaffedRoom<'_>> {
+        let room = |kind, index, level, shifts| StaffedRoom {
+            room: Room { kind, index },
+            level,
+            shifts,
+            boosts: &[],
+            products: Products::None,
+        };
+
+        let mut rooms = vec![room(
+            FacilityKind::Control,
+            0,
+            self.cc.level,
+            &self.cc.shifts,
+        )];
+        rooms.extend((self.tp.iter().enumerate()).map(|(i, tp)| StaffedRoom {
+            boosts: &tp.boosts,
+            products: Products::Trading(&tp.products),
+            ..room(FacilityKind::Trading, i, tp.level, &tp.shifts)
+        }));
+        rooms.extend((self.fac.iter().enumerate()).map(|(i, fac)| StaffedRoom {
+            boosts: &fac.boosts,
+            products: Products::Factory(&fac.products),
+            ..room(FacilityKind::Manufacture, i, fac.level, &fac.shifts)
+        }));
+        rooms.extend(
+            (self.pp.iter().enumerate())
+                .map(|(i, pp)| room(FacilityKind::Power, i, pp.level, &pp.shifts)),
+        );
+        rooms.push(room(
+            FacilityKind::Meeting,
+            0,
+            self.rr.level,
+            &self.rr.shifts,
+        ));
+        rooms.push(room(
+            FacilityKind::Hire,
+            0,
+            self.office.level,
+            &self.office.shifts,
+        ));
+        rooms.extend(
+            (self.dorm.iter().enumerate())
+                .map(|(i, dorm)| room(FacilityKind::Dormitory, i, dorm.level, &dorm.shifts)),
+        );
+
+        rooms.retain(|r| r.level > 0);
+        rooms
+    }
+
+    /// Returns the kind and level of every built room that operators cannot be assigned to.
+    pub(crate) fn unstaffed_rooms(&self) -> Vec<(FacilityKind, FacilityLevel)> {
+        let mut rooms = vec![
+            (FacilityKind::Workshop, self.workshop.level),
+            (FacilityKind::Training, self.train.level),
+        ];
+
+        rooms.retain(|&(_, level)| level > 0);
+        rooms
+    }
+
+    fn shifts_mut(&mut self, kind: FacilityKind, index: usize) -> Option<&mut Shifts> {
+        let shifts = match kind {
+            FacilityKind::Control if index == 0 => &mut self.cc.shifts,
+            FacilityKind::Trading => &mut self.tp.get_mut(index)?.shifts,
+            FacilityKind::Manufacture => &mut self.fac.get_mut(index)?.shifts,
+            FacilityKind::Power => &mut self.pp.get_mut(index)?.shifts,
+            FacilityKind::Meeting if index == 0 => &mut self.rr.shifts,
+            FacilityKind::Hire if index == 0 => &mut self.office.shifts,
+            FacilityKind::Dormitory => &mut self.dorm.get_mut(index)?.shifts,
+            _ => return None,
+        };
+
+        Some(shifts)
+    }
+
+    /// Assigns an operator to a slot of a room, where slots are numbered shift by shift
+    /// (see `StaffedRoom::slots`). Returns `false` if the layout has no such room.
+    pub(crate) fn assign(
+        &mut self,
+        kind: FacilityKind,
+        index: usize,
+        slot: usize,
+        char: Operator,
+    ) -> bool {
+        let Some(shifts) = self.shifts_mut(kind, index) else {
+            return false;
+        };
+
+        set_at(shifts, slot, Some(char));
+        true
+    }
+
+    /// Sets the product made by a room during a shift, given the product's name.
+    /// Returns `false` if the layout has no such room, or the room cannot make the product.
+    pub(crate) fn set_product(
+        &mut self,
+        kind: FacilityKind,
+        index: usize,
+        shift: ShiftCount,
+        product: &str,
+    ) -> bool {
+        let shift = usize::from(shift);
+
+        match kind {
+            FacilityKind::Trading => {
+                let (Some(tp), Some(product)) =
+                    (self.tp.get_mut(index), TradingProduct::from_name(product))
+                else {
+                    return false;
+                };
+                set_at(&mut tp.products, shift, Some(product));
+            }
+            FacilityKind::Manufacture => {
+                let (Some(fac), Some(product)) =
+                    (self.fac.get_mut(index), FactoryProduct::from_name(product))
+                else {
+                    return false;
+                };
+                set_at(&mut fac.products, shift, Some(product));
+            }
+            _ => return false,
+        }
+
+        true
+    }
+}
+
 impl Default for Layout {
     fn default() -> Self {
         Self {
@@ -165,17 +502,248 @@ impl Default for Save {
             drones: 200,
             max_shift: 12,
             interval: 720,
+            durations: Box::default(),
+            clock: None,
+        }
+    }
+}
+
+impl Save {
+    pub(crate) fn with_layout(layout: Layout) -> Self {
+        Self {
+            layout,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a save with one shift per given duration.
+    /// If every shift lasts as long, the duration is stored as the save's interval.
+    pub(crate) fn with_shifts(layout: Layout, durations: Vec<Minutes>) -> Self {
+        let default = Self::default();
+        let max_shift =
+            ShiftCount::try_from(durations.len()).expect("Failed to convert shift count");
+        let interval = durations.first().copied().unwrap_or(default.interval);
+        let durations = if durations.iter().all(|&d| d == interval) {
+            Box::default()
+        } else {
+            durations.into_boxed_slice()
+        };
+
+        Self {
+            layout,
+            max_shift,
+            interval,
+            durations,
+            ..default
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Error)]
+pub enum InvalidSave {
+    #[error("Expected {expected} shift durations, but found {found}")]
+    DurationCount { expected: ShiftCount, found: usize },
+
+    #[error("Shift durations should be numbers from 1 to {MAX_SHIFT_DURATION}")]
+    DurationRange,
+
+    #[error("The shift interval should be a number from 1 to {MAX_SHIFT_DURATION}")]
+    IntervalRange,
+
+    #[error("The rotation start time is out of range")]
+    StartTime,
+}
+
+impl Save {
+    /// # Errors
+    /// Returns error if:
+    /// - Shift durations are given, but not one for every shift
+    /// - The shift interval is out of range
+    /// - A shift duration is out of range
+    /// - The rotation start time is out of range
+    pub(crate) fn validate(&self) -> Result<(), InvalidSave> {
+        if let Some(clock) = self.clock {
+            if DateTime::from_timestamp(clock.start, 0).is_none() {
+                return Err(InvalidSave::StartTime);
+            }
+        }
+
+        if !(1..=MAX_SHIFT_DURATION).contains(&self.interval) {
+            return Err(InvalidSave::IntervalRange);
+        }
+
+        if self.durations.is_empty() {
+            return Ok(());
+        }
+
+        if self.durations.len() != usize::from(self.max_shift) {
+            return Err(InvalidSave::DurationCount {
+                expected: self.max_shift,
+                found: self.durations.len(),
+            });
+        }
+
+        if self
+            .durations
+            .iter()
+            .any(|d| !(1..=MAX_SHIFT_DURATION).contains(d))
+        {
+            return Err(InvalidSave::DurationRange);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the duration of the given shift.
+    pub(crate) fn shift_duration(&self, shift: ShiftCount) -> Minutes {
+        self.durations
+            .get(usize::from(shift))
+            .copied()
+            .unwrap_or(self.interval)
+    }
+}
+
 type BincodeConfig = Configuration<LittleEndian, Varint, Limit<MAX_SAVE_SIZE>>;
 
 const BINCODE_CONFIG: BincodeConfig = standard().with_limit();
 
+// Blobs written before save data was versioned start directly with the encoded `Save`,
+// whose first byte (the control center level) can never take this value.
+const SAVE_MAGIC: [u8; 4] = *b"RIIC";
+
+/// Format version of newly-written save data.
+/// Bump this whenever the shape of `Save` changes, and add a migration from the old shape.
+const SAVE_VERSION: u16 = 3;
+
+#[derive(Encode, Decode)]
+struct Envelope {
+    magic: [u8; 4],
+    version: u16,
+    payload: Vec<u8>, // `Save` encoded in the shape of `version`
+}
+
+#[derive(Debug, Error)]
+pub enum SaveError {
+    #[error("Save data has a malformed header")]
+    Header,
+
+    #[error("Save data has format version {0}, which is newer than this app supports")]
+    UnsupportedVersion(u16),
+
+    #[error("Save data (format version {version}) could not be decoded: {source}")]
+    Decode {
+        version: u16,
+        #[source]
+        source: DecodeError,
+    },
+
+    #[error("Save data (format version {0}) has unexpected trailing bytes")]
+    TrailingBytes(u16),
+}
+
+// Shape of `Save` in format version 1
+#[derive(Decode)]
+struct SaveV1 {
+    layout: Layout,
+    chars: Box<[CharData]>,
+    drones: DroneCount,
+    max_shift: ShiftCount,
+    interval: Minutes,
+}
+
+// Shape of `Save` in format version 2, which added per-shift durations
+#[derive(Decode)]
+struct SaveV2 {
+    layout: Layout,
+    chars: Box<[CharData]>,
+    drones: DroneCount,
+    max_shift: ShiftCount,
+    interval: Minutes,
+    durations: Box<[Minutes]>,
+}
+
+impl From<SaveV1> for SaveV2 {
+    fn from(old: SaveV1) -> Self {
+        Self {
+            layout: old.layout,
+            chars: old.chars,
+            drones: old.drones,
+            max_shift: old.max_shift,
+            interval: old.interval,
+            durations: Box::default(),
+        }
+    }
+}
+
+impl From<SaveV2> for Save {
+    fn from(old: SaveV2) -> Self {
+        Self {
+            layout: old.layout,
+            chars: old.chars,
+            drones: old.drones,
+            max_shift: old.max_shift,
+            interval: old.interval,
+            durations: old.durations,
+            clock: None,
+        }
+    }
+}
+
+// Decodes a value only if it spans the entire payload.
+// Otherwise, a payload of a newer shape could be misread as an older shape.
+fn decode_exact<T: Decode>(payload: &[u8], version: u16) -> Result<T, SaveError> {
+    match decode_from_slice(payload, BINCODE_CONFIG) {
+        Ok((value, len)) if len == payload.len() => Ok(value),
+        Ok(_) => Err(SaveError::TrailingBytes(version)),
+        Err(source) => Err(SaveError::Decode { version, source }),
+    }
+}
+
+// Decodes a payload of the given format version and migrates it to the current shape.
+fn decode_version(payload: &[u8], version: u16) -> Result<Save, SaveError> {
+    match version {
+        1 => decode_exact::<SaveV1>(payload, version).map(|old| SaveV2::from(old).into()),
+        2 => decode_exact::<SaveV2>(payload, version).map(Into::into),
+        SAVE_VERSION => decode_exact(payload, version),
+        _ => Err(SaveError::UnsupportedVersion(version)),
+    }
+}
+
+impl Save {
+    /// Encodes save data with a versioned envelope.
+    pub(crate) fn to_blob(&self) -> Result<Vec<u8>, EncodeError> {
+        let envelope = Envelope {
+            magic: SAVE_MAGIC,
+            version: SAVE_VERSION,
+            payload: encode_to_vec(self, BINCODE_CONFIG)?,
+        };
+
+        encode_to_vec(envelope, BINCODE_CONFIG)
+    }
+
+    /// Decodes save data written by any version of the app.
+    ///
+    /// # Errors
+    /// Returns error if the data has an unsupported format version or cannot be decoded.
+    pub(crate) fn from_blob(blob: &[u8]) -> Result<Self, SaveError> {
+        if blob.starts_with(&SAVE_MAGIC) {
+            let envelope: Envelope = decode_exact(blob, 0).map_err(|_| SaveError::Header)?;
+            return decode_version(&envelope.payload, envelope.version);
+        }
+
+        // Unversioned blobs do not record their shape, so try the newest shape first
+        (1..SAVE_VERSION)
+            .rev()
+            .fold(decode_version(blob, SAVE_VERSION), |result, version| {
+                result.or_else(|e| decode_version(blob, version).map_err(|_| e))
+            })
+    }
+}
+
 impl ToSql for Save {
     fn to_sql(&self) -> Result<ToSqlOutput<'_>, SqlError> {
-        let data = encode_to_vec(self, BINCODE_CONFIG)
+        let data = self
+            .to_blob()
             .map_err(|e| SqlError::ToSqlConversionFailure(e.into()))?;
 
         Ok(ToSqlOutput::Owned(Value::Blob(data)))
@@ -184,8 +752,132 @@ impl ToSql for Save {
 
 impl FromSql for Save {
     fn column_result(value: ValueRef<'_>) -> Result<Self, FromSqlError> {
-        decode_from_slice(value.as_blob()?, BINCODE_CONFIG)
-            .map(|data| data.0)
-            .map_err(|e| FromSqlError::Other(e.into()))
+        Self::from_blob(value.as_blob()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_save() -> Save {
+        let mut save = Save::with_shifts(Layout::upgraded(2, 4, 3), vec![480, 480, 240, 240]);
+        save.layout
+            .assign(FacilityKind::Trading, 0, 0, "char_102_texas".into());
+        save.chars = Box::new([CharData {
+            char: "char_102_texas".into(),
+            tier: 2,
+        }]);
+        save.clock = Some(Clock {
+            start: 1_700_000_000,
+            server: Server::US,
+        });
+        save
+    }
+
+    fn envelope(version: u16, payload: Vec<u8>) -> Vec<u8> {
+        let envelope = Envelope {
+            magic: SAVE_MAGIC,
+            version,
+            payload,
+        };
+        encode_to_vec(envelope, BINCODE_CONFIG).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_envelope() {
+        let save = sample_save();
+
+        let blob = save.to_blob().unwrap();
+
+        assert!(blob.starts_with(&SAVE_MAGIC));
+        assert!(Save::from_blob(&blob).unwrap() == save);
+    }
+
+    #[test]
+    fn reads_unversioned_blobs() {
+        let save = sample_save();
+        let current = encode_to_vec(&save, BINCODE_CONFIG).unwrap();
+        assert!(Save::from_blob(&current).unwrap() == save);
+
+        // Blobs written before per-shift durations and start times were added
+        let v1 = encode_to_vec(
+            (
+                save.layout.clone(),
+                save.chars.clone(),
+                save.drones,
+                save.max_shift,
+                save.interval,
+            ),
+            BINCODE_CONFIG,
+        )
+        .unwrap();
+        let decoded = Save::from_blob(&v1).unwrap();
+        assert!(decoded.layout == save.layout);
+        assert!(decoded.chars == save.chars);
+        assert!(decoded.durations.is_empty());
+        assert!(decoded.clock.is_none());
+    }
+
+    #[test]
+    fn migrates_older_versions() {
+        let save = sample_save();
+        let v2 = encode_to_vec(
+            (
+                save.layout.clone(),
+                save.chars.clone(),
+                save.drones,
+                save.max_shift,
+                save.interval,
+                save.durations.clone(),
+            ),
+            BINCODE_CONFIG,
+        )
+        .unwrap();
+
+        let decoded = Save::from_blob(&envelope(2, v2)).unwrap();
+
+        assert!(decoded.durations == save.durations);
+        assert!(decoded.clock.is_none());
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let payload = encode_to_vec(sample_save(), BINCODE_CONFIG).unwrap();
+
+        let result = Save::from_blob(&envelope(SAVE_VERSION + 1, payload));
+
+        assert!(matches!(result, Err(SaveError::UnsupportedVersion(v)) if v == SAVE_VERSION + 1));
+    }
+
+    #[test]
+    fn rejects_corrupted_blobs() {
+        let blob = sample_save().to_blob().unwrap();
+
+        assert!(matches!(
+            Save::from_blob(&blob[..blob.len() - 1]),
+            Err(SaveError::Header)
+        ));
+
+        let mut payload = encode_to_vec(sample_save(), BINCODE_CONFIG).unwrap();
+        payload.push(0);
+        assert!(matches!(
+            Save::from_blob(&envelope(SAVE_VERSION, payload)),
+            Err(SaveError::TrailingBytes(SAVE_VERSION))
+        ));
+    }
+
+    #[test]
+    fn rejects_intervals_out_of_range() {
+        let mut save = sample_save();
+        assert!(save.validate().is_ok());
+
+        for interval in [0, MAX_SHIFT_DURATION + 1] {
+            save.interval = interval;
+            assert!(matches!(save.validate(), Err(InvalidSave::IntervalRange)));
+        }
+
+        save.interval = MAX_SHIFT_DURATION;
+        assert!(save.validate().is_ok());
     }
 }
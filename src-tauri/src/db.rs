@@ -1,140 +1,715 @@
-use crate::{base::Save, MAX_SAVE_SIZE};
-use ahash::HashSet;
+use crate::{
+    base::{InvalidSave, Save, SaveError},
+    gamedata::Tier,
+    health::{self, Integrity},
+    history::History,
+    location, revision,
+    roster::get_resolved_save,
+    schema::migrate,
+    search,
+    share::ShareError,
+    trash, MAX_SAVE_SIZE,
+};
+use ahash::{HashMap, HashSet};
+use bincode::error::EncodeError;
 use chrono::{DateTime, Utc};
 use parking_lot::Mutex;
 use rusqlite::{
-    config::DbConfig, limits::Limit, Connection, DatabaseName, Error as SqlError, ErrorCode,
+    config::DbConfig, limits::Limit, types::Type, Connection, DatabaseName, Error as SqlError,
+    ErrorCode, InterruptHandle, OptionalExtension, Row,
+};
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use std::{
+    borrow::Cow,
+    error::Error as _,
+    fs::{self, read_dir, File},
+    io::{self, BufReader},
+    ops::Deref,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Sender},
+        Arc,
+    },
+    thread,
 };
-use serde::Serialize;
-use std::{borrow::Cow, fs::File, io::BufWriter, ops::Deref};
-use tauri::{api::path::download_dir, utils::platform::current_exe, InvokeError, State};
+use strum::IntoStaticStr;
+use tauri::{api::path::download_dir, async_runtime::spawn_blocking, Config, State};
 use thiserror::Error;
+use tokio::sync::oneshot;
+
+// Builds a query for the metadata of saves `s` that match a `SaveFilter`, followed by `$columns`.
+// The folder, text and tags of the filter are bound by `SaveFilter::params`.
+macro_rules! select_filtered {
+    ($columns:literal) => {
+        concat!(
+            "SELECT s.name, s.created, s.last_modified, s.description, s.folder, (
+                SELECT json_group_array(name) FROM (
+                    SELECT t.name FROM save_tag st JOIN tag t ON st.tag_id = t.id
+                    WHERE st.save_id = s.id ORDER BY t.name
+                )
+            ) AS tags",
+            $columns,
+            " FROM save s
+            WHERE s.deleted IS NULL
+            AND (?1 IS NULL OR s.folder = ?1)
+            AND (?2 IS NULL OR instr(lower(s.name), lower(?2)) OR instr(lower(s.description), lower(?2)))
+            AND NOT EXISTS (
+                SELECT 1 FROM json_each(?3) f WHERE NOT EXISTS (
+                    SELECT 1 FROM save_tag st JOIN tag t ON st.tag_id = t.id
+                    WHERE st.save_id = s.id AND t.name = f.value
+                )
+            )"
+        )
+    };
+}
+pub(crate) use select_filtered;
+
+type Job = Box<dyn FnOnce(&mut Connection) + Send>;
+
+/// Identifies a cancellable operation.
+/// It is chosen by the frontend, so that the operation can be cancelled while it is pending,
+/// and must not be shared with another pending operation.
+pub(crate) type OperationId = u32;
+
+/// Handle to the app database, whose connection is owned by a dedicated worker thread.
+/// Database operations are queued and run one at a time, so that they never block the UI.
+pub struct Database {
+    jobs: Mutex<Sender<Job>>,
+    interrupt: Arc<Mutex<InterruptHandle>>, // Interrupts queries on the worker's connection
+    operations: Arc<Mutex<Operations>>,
+}
+
+#[derive(Default)]
+struct Operations {
+    flags: HashMap<OperationId, CancelFlag>, // Cancellable operations that are queued or running
+    running: Option<OperationId>,
+}
+
+// Forgets a cancellable operation once it stops, even if it panics.
+struct OperationGuard {
+    operations: Arc<Mutex<Operations>>,
+    id: OperationId,
+}
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        let mut operations = self.operations.lock();
+        operations.running = None;
+        operations.flags.remove(&self.id);
+    }
+}
 
-pub struct Database(Mutex<Connection>);
+/// Set when a cancellable operation should be cancelled.
+/// Long-running operations should check this between steps that do not query the database,
+/// since only running queries are interrupted.
+#[derive(Clone, Default)]
+pub(crate) struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub(crate) fn check(&self) -> DbResult<()> {
+        if self.0.load(Ordering::Relaxed) {
+            return Err(DbError::Cancelled);
+        }
+        Ok(())
+    }
+}
 
 impl Database {
+    /// Opens the database at the location resolved by `location::resolve`.
+    ///
     /// # Errors
     /// Returns error if:
+    /// - The database location cannot be determined or created
     /// - A connection to the database cannot be opened
     /// - Database configuration cannot be set
-    /// - SQL statements executed during setup fail
+    /// - The database was created by a newer version of the app
+    /// - Database migrations fail
+    /// - Saves cannot be reindexed for searching
+    /// - Expired saves cannot be purged from the trash
     /// - SQL statements cannot be prepared and cached
-    pub fn setup() -> Result<Self, SqlError> {
-        let db_path = current_exe()
-            .expect("Failed to get the currently-running binary path")
-            .with_file_name("data.db");
-        let conn = Connection::open(db_path)?;
-
-        let max_save_size = MAX_SAVE_SIZE
-            .try_into()
-            .expect("Failed to convert MAX_SAVE_SIZE to i32");
-
-        // Set configuration
-        conn.set_db_config(DbConfig::SQLITE_DBCONFIG_DEFENSIVE, true)?;
-        conn.set_db_config(DbConfig::SQLITE_DBCONFIG_TRUSTED_SCHEMA, false)?;
-        conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_TRIGGER, false)?;
-        conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_VIEW, false)?;
-        conn.set_limit(Limit::SQLITE_LIMIT_LENGTH, max_save_size);
-        conn.set_limit(Limit::SQLITE_LIMIT_SQL_LENGTH, max_save_size);
-        conn.pragma_update(Some(DatabaseName::Main), "journal_mode", "wal")?;
-        conn.pragma_update(Some(DatabaseName::Main), "journal_size_limit", 0)?;
-        conn.pragma_update(Some(DatabaseName::Main), "locking_mode", "exclusive")?;
-        conn.pragma_update(Some(DatabaseName::Main), "synchronous", "normal")?;
-
-        // Initialize database
-        conn.execute_batch(
-            "BEGIN;
-            CREATE TABLE IF NOT EXISTS save (
-                name            TEXT NOT NULL UNIQUE,
-                created         TEXT NOT NULL,
-                last_modified   TEXT NOT NULL,
-                data            BLOB NOT NULL
-            ) STRICT;
-            CREATE INDEX IF NOT EXISTS idx_name_data ON save (
-                name, data
-            );
-            COMMIT;",
-        )?;
-
-        // Check taken names
-        conn.prepare_cached("SELECT name FROM save")?;
-        // Fetch all
-        conn.prepare_cached("SELECT name, created, last_modified FROM save")?;
-        // Create
-        conn.prepare_cached(
-            "INSERT INTO save (
-                name, created, last_modified, data
-            ) VALUES (
-                ?1, ?2, ?3, ?4
-            )",
-        )?;
-        // Get
-        conn.prepare_cached("SELECT data FROM save WHERE name = ?1")?;
-        // Rename
-        conn.prepare_cached("UPDATE save SET name = ?2 WHERE name = ?1")?;
-        // Update
-        conn.prepare_cached("UPDATE save SET last_modified = ?2, data = ?3 WHERE name = ?1")?;
-        // Delete
-        conn.prepare_cached("DELETE FROM save WHERE name = ?1")?;
-
-        Ok(Self(Mutex::new(conn)))
+    pub(crate) fn setup(config: &Config) -> Result<(Self, Integrity), SetupError> {
+        let db_path = location::resolve(config)?;
+        let (conn, integrity) = open_connection(&db_path)?;
+
+        Ok((Self::spawn(conn), integrity))
+    }
+
+    /// Opens a temporary, read-only database that only lives in memory.
+    /// This lets the app start and report why the database file cannot be opened,
+    /// without accepting edits that would be lost when the app exits.
+    ///
+    /// # Errors
+    /// Returns error if the database cannot be configured.
+    pub fn read_only() -> Result<Self, SetupError> {
+        let (conn, _) = open_connection(Path::new(":memory:"))?;
+        conn.pragma_update(Some(DatabaseName::Main), "query_only", true)?;
+
+        Ok(Self::spawn(conn))
+    }
+
+    fn spawn(mut conn: Connection) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let interrupt = Arc::new(Mutex::new(conn.get_interrupt_handle()));
+        let worker_interrupt = Arc::clone(&interrupt);
+
+        thread::Builder::new()
+            .name("database".into())
+            .spawn(move || {
+                for job in receiver {
+                    job(&mut conn);
+                    // The connection is replaced when switching databases
+                    *worker_interrupt.lock() = conn.get_interrupt_handle();
+                }
+            })
+            .expect("Failed to spawn database worker thread");
+
+        Self {
+            jobs: Mutex::new(sender),
+            interrupt,
+            operations: Arc::default(),
+        }
+    }
+
+    fn queue<T, F>(&self, job: F) -> oneshot::Receiver<DbResult<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> DbResult<T> + Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        self.jobs
+            .lock()
+            .send(Box::new(move |conn| {
+                // A panicking job fails on its own instead of stopping the worker thread.
+                // Its open transaction (if any) is rolled back as the job unwinds.
+                let result = panic::catch_unwind(AssertUnwindSafe(|| job(conn)))
+                    .unwrap_or(Err(DbError::Panicked))
+                    .map_err(DbError::into_cancelled);
+                // The command may have been dropped, in which case no one needs the result
+                sender.send(result).ok();
+            }))
+            .expect("Database worker thread has stopped");
+
+        receiver
+    }
+
+    /// Queues an operation on the database and waits for its result.
+    pub(crate) async fn run<T, F>(&self, job: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> DbResult<T> + Send + 'static,
+    {
+        self.queue(job)
+            .await
+            .expect("Database worker thread has stopped")
+    }
+
+    /// Queues an operation on the database and blocks the current thread until it finishes.
+    /// This must not be called from async code.
+    pub(crate) fn run_blocking<T, F>(&self, job: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> DbResult<T> + Send + 'static,
+    {
+        self.queue(job)
+            .blocking_recv()
+            .expect("Database worker thread has stopped")
+    }
+
+    /// Queues an operation that can be cancelled with `cancel_operation` and waits for its result.
+    /// The operation is given a flag that is set when it is cancelled.
+    pub(crate) async fn run_cancellable<T, F>(&self, id: OperationId, job: F) -> DbResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection, &CancelFlag) -> DbResult<T> + Send + 'static,
+    {
+        let cancelled = CancelFlag::default();
+        self.operations.lock().flags.insert(id, cancelled.clone());
+        let operations = Arc::clone(&self.operations);
+
+        self.run(move |conn| {
+            operations.lock().running = Some(id);
+            let _guard = OperationGuard { operations, id };
+
+            // The operation may have been cancelled while it was queued
+            cancelled.check()?;
+            job(conn, &cancelled)
+        })
+        .await
     }
 
     /// # Errors
     /// Returns error if:
     /// - Database optimization fails
-    pub fn teardown(&self) -> Result<(), SqlError> {
-        self.0.lock().execute("PRAGMA optimize;", ()).map(|_| ())
+    pub fn teardown(&self) -> Result<(), DbError> {
+        self.run_blocking(|conn| {
+            conn.execute("PRAGMA optimize;", ())
+                .map_err(DbError::Execution)?;
+            Ok(())
+        })
+    }
+}
+
+/// Cancels a cancellable operation (such as importing or exporting many saves) with the given ID,
+/// if it is queued or running.
+/// Operations that are cancelled return a `Cancelled` error.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn cancel_operation(db: State<'_, Database>, id: OperationId) {
+    let operations = db.operations.lock();
+
+    if let Some(cancelled) = operations.flags.get(&id) {
+        cancelled.0.store(true, Ordering::Relaxed);
+
+        // Only the running operation is interrupted, so that other operations are not affected
+        if operations.running == Some(id) {
+            db.interrupt.lock().interrupt();
+        }
     }
 }
 
+/// Opens, configures and migrates the database file at `path`, creating it if needed.
+/// Returns the connection, along with the outcome of the integrity check that runs before
+/// the database is migrated.
+pub(crate) fn open_connection(path: &Path) -> Result<(Connection, Integrity), SetupError> {
+    let mut conn = Connection::open(path)?;
+
+    let max_save_size = MAX_SAVE_SIZE
+        .try_into()
+        .expect("Failed to convert MAX_SAVE_SIZE to i32");
+
+    // Set configuration
+    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_DEFENSIVE, true)?;
+    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_TRUSTED_SCHEMA, false)?;
+    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_TRIGGER, false)?;
+    conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_VIEW, false)?;
+    conn.set_limit(Limit::SQLITE_LIMIT_LENGTH, max_save_size);
+    conn.set_limit(Limit::SQLITE_LIMIT_SQL_LENGTH, max_save_size);
+    conn.pragma_update(Some(DatabaseName::Main), "journal_mode", "wal")?;
+    conn.pragma_update(Some(DatabaseName::Main), "journal_size_limit", 0)?;
+    conn.pragma_update(Some(DatabaseName::Main), "locking_mode", "exclusive")?;
+    conn.pragma_update(Some(DatabaseName::Main), "synchronous", "normal")?;
+
+    // Repair corrupted indexes before anything else writes to the database
+    let integrity = health::repair(&conn);
+
+    // Initialize or upgrade database
+    migrate(&mut conn)?;
+    conn.pragma_update(Some(DatabaseName::Main), "foreign_keys", true)?;
+    search::reindex(&mut conn)?;
+    trash::purge(&conn)?;
+
+    conn.set_prepared_statement_cache_capacity(64);
+    cache_statements(&conn)?;
+
+    Ok((conn, integrity))
+}
+
+// Prepares and caches every SQL statement used by the app, so that invalid statements are
+// caught at startup.
+#[allow(clippy::too_many_lines)]
+fn cache_statements(conn: &Connection) -> Result<(), SqlError> {
+    // Check taken names
+    conn.prepare_cached("SELECT name FROM save WHERE deleted IS NULL")?;
+    // Fetch all
+    conn.prepare_cached(select_filtered!(""))?;
+    // Fetch all for archive
+    conn.prepare_cached(select_filtered!(", s.data"))?;
+    // Create
+    conn.prepare_cached(
+        "INSERT INTO save (
+            name, created, last_modified, data
+        ) VALUES (
+            ?1, ?2, ?3, ?4
+        )",
+    )?;
+    // Get
+    conn.prepare_cached("SELECT data FROM save WHERE name = ?1 AND deleted IS NULL")?;
+    // Check all
+    conn.prepare_cached("SELECT name, data FROM save WHERE deleted IS NULL")?;
+    // Rename
+    conn.prepare_cached("UPDATE save SET name = ?2 WHERE name = ?1 AND deleted IS NULL")?;
+    // Update
+    conn.prepare_cached(
+        "UPDATE save SET last_modified = ?2, data = ?3 WHERE name = ?1 AND deleted IS NULL
+        RETURNING id",
+    )?;
+    // Delete
+    conn.prepare_cached("UPDATE save SET deleted = ?2 WHERE name = ?1 AND deleted IS NULL")?;
+    // Record revision
+    conn.prepare_cached(
+        "INSERT INTO save_revision (
+            save_id, created, data
+        ) SELECT id, last_modified, data FROM save WHERE name = ?1 AND deleted IS NULL AND data IS NOT ?2",
+    )?;
+    // Prune revisions by count
+    conn.prepare_cached(
+        "DELETE FROM save_revision
+        WHERE save_id = (SELECT id FROM save WHERE name = ?1 AND deleted IS NULL)
+        AND id NOT IN (
+            SELECT r.id FROM save_revision r JOIN save s ON r.save_id = s.id
+            WHERE s.name = ?1 AND s.deleted IS NULL ORDER BY r.created DESC, r.id DESC LIMIT ?2
+        )",
+    )?;
+    // Prune revisions by age
+    conn.prepare_cached(
+        "DELETE FROM save_revision
+        WHERE created < ?1 AND save_id NOT IN (SELECT save_id FROM quarantine)",
+    )?;
+    // List revisions
+    conn.prepare_cached(
+        "SELECT r.id, r.created FROM save_revision r JOIN save s ON r.save_id = s.id
+        WHERE s.name = ?1 AND s.deleted IS NULL ORDER BY r.created DESC, r.id DESC",
+    )?;
+    // Get revision
+    conn.prepare_cached(
+        "SELECT r.data FROM save_revision r JOIN save s ON r.save_id = s.id
+        WHERE s.name = ?1 AND s.deleted IS NULL AND r.id = ?2",
+    )?;
+    // Fetch templates
+    conn.prepare_cached("SELECT name FROM template ORDER BY created")?;
+    // Get template
+    conn.prepare_cached("SELECT data FROM template WHERE name = ?1")?;
+    // Create template
+    conn.prepare_cached(
+        "INSERT INTO template (
+            name, created, data
+        ) VALUES (
+            ?1, ?2, ?3
+        )",
+    )?;
+    // Delete template
+    conn.prepare_cached("DELETE FROM template WHERE name = ?1")?;
+    // Set description
+    conn.prepare_cached("UPDATE save SET description = ?2 WHERE name = ?1 AND deleted IS NULL")?;
+    // Set folder
+    conn.prepare_cached("UPDATE save SET folder = ?2 WHERE name = ?1 AND deleted IS NULL")?;
+    // Clear tags
+    conn.prepare_cached(
+        "DELETE FROM save_tag WHERE save_id = (SELECT id FROM save WHERE name = ?1 AND deleted IS NULL)",
+    )?;
+    // Create tag
+    conn.prepare_cached("INSERT OR IGNORE INTO tag (name) VALUES (?1)")?;
+    // Add tag
+    conn.prepare_cached(
+        "INSERT OR IGNORE INTO save_tag (
+            save_id, tag_id
+        ) SELECT s.id, t.id FROM save s, tag t WHERE s.name = ?1 AND s.deleted IS NULL AND t.name = ?2",
+    )?;
+    // Delete unused tags
+    conn.prepare_cached("DELETE FROM tag WHERE id NOT IN (SELECT tag_id FROM save_tag)")?;
+    // Fetch tags
+    conn.prepare_cached(
+        "SELECT t.name FROM tag t WHERE EXISTS (
+            SELECT 1 FROM save_tag st JOIN save s ON st.save_id = s.id
+            WHERE st.tag_id = t.id AND s.deleted IS NULL
+        ) ORDER BY t.name",
+    )?;
+    // Fetch folders
+    conn.prepare_cached(
+        "SELECT DISTINCT folder FROM save WHERE deleted IS NULL AND folder IS NOT NULL ORDER BY folder",
+    )?;
+    // Clear facts
+    conn.prepare_cached("DELETE FROM save_fact WHERE save_id = ?1")?;
+    // Index fact
+    conn.prepare_cached(
+        "INSERT INTO save_fact (
+            save_id, kind, value
+        ) VALUES (
+            ?1, ?2, ?3
+        )",
+    )?;
+    // Search facts
+    conn.prepare_cached(
+        "SELECT s.name FROM save_fact f JOIN save s ON f.save_id = s.id
+        WHERE f.kind = ?1 AND f.value = ?2 AND s.deleted IS NULL",
+    )?;
+    // List trash
+    conn.prepare_cached(
+        "SELECT id, name, deleted FROM save
+        WHERE deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)
+        ORDER BY deleted DESC",
+    )?;
+    // Get trashed name
+    conn.prepare_cached(
+        "SELECT name FROM save
+        WHERE id = ?1 AND deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)",
+    )?;
+    // Restore
+    conn.prepare_cached("UPDATE save SET name = ?2, deleted = NULL WHERE id = ?1")?;
+    // Empty trash
+    conn.prepare_cached(
+        "DELETE FROM save
+        WHERE deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)",
+    )?;
+    // Purge trash
+    conn.prepare_cached(
+        "DELETE FROM save WHERE deleted < ?1 AND id NOT IN (SELECT save_id FROM quarantine)",
+    )?;
+    // Get setting
+    conn.prepare_cached("SELECT value FROM setting WHERE key = ?1")?;
+    // Set setting
+    conn.prepare_cached(
+        "INSERT INTO setting (
+            key, value
+        ) VALUES (
+            ?1, ?2
+        ) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+    )?;
+    // List quarantine
+    conn.prepare_cached(
+        "SELECT q.id, s.name, q.reason, q.quarantined, (
+            SELECT count(*) FROM save_revision r WHERE r.save_id = s.id
+        ) AS revisions FROM quarantine q JOIN save s ON q.save_id = s.id
+        ORDER BY q.quarantined DESC",
+    )?;
+    // Get quarantined save
+    conn.prepare_cached(
+        "SELECT s.id, s.name, s.data FROM quarantine q JOIN save s ON q.save_id = s.id
+        WHERE q.id = ?1",
+    )?;
+    // List readable revisions
+    conn.prepare_cached(
+        "SELECT data FROM save_revision WHERE save_id = ?1 ORDER BY created DESC, id DESC",
+    )?;
+    // Release quarantined
+    conn.prepare_cached("DELETE FROM quarantine WHERE id = ?1")?;
+    // Delete quarantined
+    conn.prepare_cached(
+        "DELETE FROM save WHERE id = (SELECT save_id FROM quarantine WHERE id = ?1)",
+    )?;
+    // List roster
+    conn.prepare_cached("SELECT char, tier FROM roster ORDER BY char")?;
+    // Set roster operator
+    conn.prepare_cached(
+        "INSERT INTO roster (
+            char, tier
+        ) VALUES (
+            ?1, ?2
+        ) ON CONFLICT (char) DO UPDATE SET tier = excluded.tier",
+    )?;
+    // Delete roster operator
+    conn.prepare_cached("DELETE FROM roster WHERE char = ?1")?;
+    // Find saves affected by roster operator
+    conn.prepare_cached(
+        "SELECT s.name, EXISTS (
+            SELECT 1 FROM save_fact f WHERE f.save_id = s.id AND f.kind = ?2 AND f.value = ?1
+        ) AS assigned FROM save s
+        WHERE s.deleted IS NULL AND NOT EXISTS (
+            SELECT 1 FROM save_fact f WHERE f.save_id = s.id AND f.kind = ?3 AND f.value = ?1
+        ) ORDER BY s.name",
+    )?;
+
+    Ok(())
+}
+
 #[derive(Debug, Error)]
+pub enum SetupError {
+    #[error(transparent)]
+    Sql(#[from] SqlError),
+
+    #[error("The database was created by a newer version of the app (schema version {found}, but only up to {supported} is supported)")]
+    NewerSchema { found: usize, supported: usize },
+
+    #[error("Failed to back up the database before upgrading it")]
+    Backup(#[source] SqlError),
+
+    #[error("Upgrading the database to schema version {0} left dangling references")]
+    ForeignKeyViolation(usize),
+
+    #[error("The app data directory could not be located")]
+    NoDataDir,
+
+    #[error("Failed to create the database directory")]
+    DataDir(#[source] io::Error),
+}
+
+// Each variant's name doubles as its `code`, a stable identifier the frontend can branch on.
+#[derive(Debug, Error, IntoStaticStr)]
 pub enum DbError {
     #[error("Failed to execute query statement")]
-    Execution,
+    Execution(#[source] SqlError),
 
     #[error("An error occurred while fetching saves")]
-    Fetching,
+    Fetching(#[source] SqlError),
+
+    #[error("The save could not be read: {0}")]
+    Decoding(#[source] SaveError),
 
     #[error("An error occurred while creating a new save")]
-    Creation,
+    Creation(#[source] SqlError),
 
     #[error("An error occurred while renaming the save")]
-    Renaming,
+    Renaming(#[source] SqlError),
 
     #[error("Another save with the same name already exists")]
     DuplicateName,
 
+    #[error("Another template with the same name already exists")]
+    DuplicateTemplate,
+
     #[error("An error occurred while updating the save")]
-    Update,
+    Update(#[source] SqlError),
+
+    #[error("The save is invalid: {0}")]
+    Invalid(#[source] InvalidSave),
+
+    #[error("The save has no rotation start time")]
+    NoStartTime,
+
+    #[error("Schedules can cover at most {0} days")]
+    ScheduleLength(u32),
+
+    #[error("There is no edit to undo or redo")]
+    NoHistory,
 
     #[error("An error occurred while deleting the save")]
-    Deletion,
+    Deletion(#[source] SqlError),
+
+    #[error("An error occurred while restoring the save")]
+    Restoration(#[source] SqlError),
+
+    #[error("An error occurred while changing the setting")]
+    Setting(#[source] SqlError),
 
     #[error("The download directory could not be located")]
     NoExportTarget,
 
     #[error("An error occurred while exporting the save")]
-    Export,
+    Export(#[source] io::Error),
+
+    #[error("The file could not be read")]
+    ImportRead(#[source] io::Error),
+
+    #[error("The file is not a valid save export")]
+    ImportFormat(#[source] Option<serde_json::Error>),
+
+    #[error("An error occurred while backing up the database")]
+    Backup(#[source] SqlError),
+
+    #[error("The file is not a valid database backup")]
+    InvalidBackup(#[source] Option<SqlError>),
+
+    #[error("The backup was created by a newer version of the app")]
+    NewerBackup,
+
+    #[error("An error occurred while restoring the database")]
+    BackupRestore(#[source] SetupError),
+
+    #[error("Database names can only contain letters, numbers, spaces, hyphens and underscores, and cannot start or end with a space")]
+    DatabaseName,
+
+    #[error("This database name is reserved by the operating system")]
+    ReservedDatabaseName,
+
+    #[error("The app is using a temporary database, which is not stored in a file")]
+    NoDatabaseFile,
+
+    #[error("The database directory could not be read")]
+    DatabaseList(#[source] io::Error),
+
+    #[error("The database could not be opened: {0}")]
+    DatabaseOpen(#[source] SetupError),
+
+    #[error("The database choice could not be saved")]
+    DatabaseChoice(#[source] io::Error),
+
+    #[error("The operation was cancelled")]
+    Cancelled,
+
+    #[error("An unexpected error occurred in the database")]
+    Panicked,
+
+    #[error("An error occurred while creating the share code")]
+    ShareEncode(#[source] EncodeError),
+
+    #[error("The share code could not be read: {0}")]
+    ShareCode(#[source] ShareError),
+
+    #[error("There is no operator with this ID")]
+    UnknownChar,
+
+    #[error("Operator tiers range from 0 to {0}")]
+    UnknownTier(Tier),
+}
+
+impl DbError {
+    // Errors caused by an interrupted query are reported as cancellations.
+    fn into_cancelled(self) -> Self {
+        let mut source = self.source();
+
+        while let Some(e) = source {
+            let code = e
+                .downcast_ref::<SqlError>()
+                .and_then(SqlError::sqlite_error_code);
+            if code == Some(ErrorCode::OperationInterrupted) {
+                return Self::Cancelled;
+            }
+            source = e.source();
+        }
+
+        self
+    }
+
+    // Describes the chain of underlying errors, e.g. the SQLite error behind a failed query.
+    fn details(&self) -> Option<String> {
+        let mut source = self.source()?;
+        let mut details = source.to_string();
+
+        while let Some(next) = source.source() {
+            details = format!("{details}: {next}");
+            source = next;
+        }
+
+        Some(details)
+    }
 }
 
-type DbResult<T> = Result<T, DbError>;
+pub(crate) type DbResult<T> = Result<T, DbError>;
 
-impl From<DbError> for InvokeError {
-    fn from(val: DbError) -> Self {
-        Self::from(val.to_string())
+// Errors reach the frontend as `{ code, message, details }`, where `message` is meant for users
+// and `details` (if any) describes the underlying cause for logs and bug reports.
+impl Serialize for DbError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("DbError", 3)?;
+        error.serialize_field("code", <&str>::from(self))?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("details", &self.details())?;
+        error.end()
     }
 }
 
 #[derive(Serialize)]
 pub struct FileData {
-    name: Box<str>,
+    pub(crate) name: Box<str>,
     created: f32,
     modified: f32,
+    description: Box<str>,
+    folder: Option<Box<str>>,
+    tags: Box<[Box<str>]>,
+}
+
+/// Criteria that saves must all match to be listed by `fetch_saves`
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SaveFilter {
+    folder: Option<Box<str>>,
+    tags: Box<[Box<str>]>,  // Saves must have every one of these tags
+    text: Option<Box<str>>, // Case-insensitive substring of the name or description
+}
+
+impl SaveFilter {
+    // Returns the parameters of a query built by `select_filtered`.
+    pub(crate) fn params(&self) -> (&Option<Box<str>>, &Option<Box<str>>, String) {
+        let tags = serde_json::to_string(&self.tags).expect("Failed to serialize tag filter");
+        (&self.folder, &self.text, tags)
+    }
 }
 
 #[allow(clippy::cast_precision_loss)]
-fn get_elapsed_time(earlier: DateTime<Utc>, later: DateTime<Utc>) -> f32 {
+pub(crate) fn get_elapsed_time(earlier: DateTime<Utc>, later: DateTime<Utc>) -> f32 {
     (later - earlier).num_milliseconds() as f32 / 1000.
 }
 
@@ -158,63 +733,116 @@ where
     Cow::Owned(new_name)
 }
 
-fn get_save_from_name<C>(conn: C, name: &str) -> DbResult<Save>
+pub(crate) fn get_save_from_name<C>(conn: C, name: &str) -> DbResult<Save>
 where
     C: Deref<Target = Connection>,
 {
-    conn.prepare_cached("SELECT data FROM save WHERE name = ?1")
-        .map_err(|_| DbError::Execution)?
+    conn.prepare_cached("SELECT data FROM save WHERE name = ?1 AND deleted IS NULL")
+        .map_err(DbError::Execution)?
         .query_row([name], |row| row.get("data"))
-        .map_err(|_| DbError::Fetching)
+        .map_err(map_fetch_error)
+}
+
+// Reports save data that could not be decoded separately from other query failures
+pub(crate) fn map_fetch_error(e: SqlError) -> DbError {
+    match e {
+        SqlError::FromSqlConversionFailure(idx, ty, err) => match err.downcast() {
+            Ok(err) => DbError::Decoding(*err),
+            Err(err) => DbError::Fetching(SqlError::FromSqlConversionFailure(idx, ty, err)),
+        },
+        e => DbError::Fetching(e),
+    }
+}
+
+// Replaces the data of a save, keeping the old data as a revision.
+// This should be called within a transaction.
+pub(crate) fn write_save(conn: &Connection, name: &str, save: &Save) -> DbResult<()> {
+    revision::record(conn, name, save)?;
+
+    let id = conn
+        .prepare_cached(
+            "UPDATE save SET last_modified = ?2, data = ?3 WHERE name = ?1 AND deleted IS NULL
+            RETURNING id",
+        )
+        .map_err(DbError::Execution)?
+        .query_row((name, Utc::now(), save), |row| row.get("id"))
+        .optional()
+        .map_err(DbError::Update)?;
+
+    if let Some(id) = id {
+        search::index(conn, id, save).map_err(DbError::Update)?;
+    }
+
+    revision::prune(conn, name)
 }
 
+/// Lists saves matching the given filter, or all saves if no filter is given.
+///
 /// # Errors
 /// Returns error if:
 /// - Invalid SQL statement is present
 /// - Database query failed
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn fetch_saves(db: State<'_, Database>) -> DbResult<Box<[FileData]>> {
-    let conn = db.0.lock();
+pub async fn fetch_saves(
+    db: State<'_, Database>,
+    filter: Option<SaveFilter>,
+) -> DbResult<Box<[FileData]>> {
+    let filter = filter.unwrap_or_default();
+    db.run(move |conn| fetch_filtered(conn, &filter)).await
+}
 
+// Lists saves matching the given filter.
+pub(crate) fn fetch_filtered(conn: &Connection, filter: &SaveFilter) -> DbResult<Box<[FileData]>> {
     let now = Utc::now();
 
     let query = conn
-        .prepare_cached("SELECT name, created, last_modified FROM save")
-        .map_err(|_| DbError::Execution)?
-        .query_and_then([], |row| {
+        .prepare_cached(select_filtered!(""))
+        .map_err(DbError::Execution)?
+        .query_and_then(filter.params(), |row| {
             Ok(FileData {
                 name: row.get("name")?,
                 created: get_elapsed_time(row.get("created")?, now),
                 modified: get_elapsed_time(row.get("last_modified")?, now),
+                description: row.get("description")?,
+                folder: row.get("folder")?,
+                tags: get_tags(row)?,
             })
         })
-        .map_err(|_| DbError::Fetching)?
+        .map_err(DbError::Fetching)?
         .collect::<Result<Box<[FileData]>, SqlError>>()
-        .map_err(|_| DbError::Fetching)?;
+        .map_err(DbError::Fetching)?;
 
     Ok(query)
 }
 
-/// # Errors
-/// Returns error if:
-/// - Invalid SQL statement is present
-/// - Database query failed
-/// - Database insertion failed
-#[tauri::command]
-#[allow(clippy::needless_pass_by_value)]
-pub fn create_save(db: State<'_, Database>) -> DbResult<()> {
-    let conn = db.0.lock();
+// Reads the tags selected by `select_filtered`.
+pub(crate) fn get_tags(row: &Row<'_>) -> Result<Box<[Box<str>]>, SqlError> {
+    let tags: String = row.get("tags")?;
 
+    serde_json::from_str(&tags)
+        .map_err(|e| SqlError::FromSqlConversionFailure(5, Type::Text, e.into()))
+}
+
+// Returns the given name, or a numbered variant of it if another save has the name.
+// Saves in the trash do not take up names.
+pub(crate) fn get_free_name(conn: &Connection, name: &str) -> DbResult<Box<str>> {
     let names = conn
-        .prepare_cached("SELECT name FROM save")
-        .map_err(|_| DbError::Execution)?
+        .prepare_cached("SELECT name FROM save WHERE deleted IS NULL")
+        .map_err(DbError::Execution)?
         .query_and_then([], |row| row.get("name"))
-        .map_err(|_| DbError::Fetching)?
+        .map_err(DbError::Fetching)?
         .collect::<Result<HashSet<Box<str>>, SqlError>>()
-        .map_err(|_| DbError::Fetching)?;
+        .map_err(DbError::Fetching)?;
+
+    Ok(get_available_name(name, |new_name| !names.contains(new_name)).into())
+}
 
-    let save_name = get_available_name("Untitled", |new_name| !names.contains(new_name));
+// Inserts a save under the given name, or a numbered variant of it if the name is taken.
+// Returns the name that the save was inserted under.
+// This should be called within a transaction.
+pub(crate) fn insert_save(conn: &Connection, name: &str, save: &Save) -> DbResult<Box<str>> {
+    let save_name = get_free_name(conn, name)?;
 
     let now = Utc::now();
 
@@ -225,22 +853,103 @@ pub fn create_save(db: State<'_, Database>) -> DbResult<()> {
             ?1, ?2, ?3, ?4
         )",
     )
-    .map_err(|_| DbError::Execution)?
-    .execute((save_name, now, now, Save::default()))
-    .map_err(|_| DbError::Creation)?;
+    .map_err(DbError::Execution)?
+    .execute((&save_name, now, now, save))
+    .map_err(DbError::Creation)?;
 
-    Ok(())
+    search::index(conn, conn.last_insert_rowid(), save).map_err(DbError::Creation)?;
+
+    Ok(save_name)
+}
+
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn create_save(db: State<'_, Database>) -> DbResult<()> {
+    db.run(|conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        insert_save(&tx, "Untitled", &Save::default())?;
+        tx.commit().map_err(DbError::Creation)
+    })
+    .await
+}
+
+/// Copies a save under a free name derived from its own.
+/// Returns the name of the copy.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn duplicate_save(db: State<'_, Database>, name: String) -> DbResult<Box<str>> {
+    db.run(move |conn| duplicate(conn, &name)).await
+}
+
+// The copy keeps the save's own roster, so that it uses the shared roster like the original.
+pub(crate) fn duplicate(conn: &mut Connection, name: &str) -> DbResult<Box<str>> {
+    let tx = conn.transaction().map_err(DbError::Execution)?;
+    let save = get_save_from_name(&*tx, name)?;
+
+    let new_name = insert_save(&tx, name, &save)?;
+    tx.commit().map_err(DbError::Creation)?;
+
+    Ok(new_name)
+}
+
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_save(db: State<'_, Database>, name: String) -> DbResult<Save> {
+    db.run(move |conn| get_save_from_name(&*conn, &name)).await
+}
+
+#[derive(Serialize)]
+pub struct FailingSave {
+    name: Box<str>,
+    reason: Box<str>,
 }
 
+/// Lists every save whose data cannot be decoded, along with the reason.
+///
 /// # Errors
 /// Returns error if:
 /// - Invalid SQL statement is present
 /// - Database query failed
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn get_save(db: State<'_, Database>, name: &str) -> DbResult<Save> {
-    let conn = db.0.lock();
-    get_save_from_name(conn, name)
+pub async fn check_saves(db: State<'_, Database>) -> DbResult<Box<[FailingSave]>> {
+    db.run(|conn| {
+        let failures = conn
+            .prepare_cached("SELECT name, data FROM save WHERE deleted IS NULL")
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| {
+                let name = row.get("name")?;
+                let data: Vec<u8> = row.get("data")?;
+
+                Ok(Save::from_blob(&data).err().map(|e| FailingSave {
+                    name,
+                    reason: e.to_string().into_boxed_str(),
+                }))
+            })
+            .map_err(DbError::Fetching)?
+            .filter_map(Result::transpose)
+            .collect::<Result<Box<[FailingSave]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(failures)
+    })
+    .await
 }
 
 /// # Errors
@@ -250,58 +959,99 @@ pub fn get_save(db: State<'_, Database>, name: &str) -> DbResult<Save> {
 /// - A save with name `new` already exists
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn rename_save(db: State<'_, Database>, old: &str, new: &str) -> DbResult<()> {
-    let conn = db.0.lock();
+pub async fn rename_save(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    old: String,
+    new: String,
+) -> DbResult<()> {
+    let names = (old.clone(), new.clone());
 
-    conn.prepare_cached("UPDATE save SET name = ?2 WHERE name = ?1")
-        .map_err(|_| DbError::Execution)?
-        .execute([old, new])
-        .map_err(|e| match e.sqlite_error_code() {
-            Some(ErrorCode::ConstraintViolation) => DbError::DuplicateName,
-            _ => DbError::Renaming,
-        })?;
+    db.run(move |conn| {
+        conn.prepare_cached("UPDATE save SET name = ?2 WHERE name = ?1 AND deleted IS NULL")
+            .map_err(DbError::Execution)?
+            .execute(names)
+            .map_err(|e| match e.sqlite_error_code() {
+                Some(ErrorCode::ConstraintViolation) => DbError::DuplicateName,
+                _ => DbError::Renaming(e),
+            })
+    })
+    .await?;
+
+    history.rename(&old, &new);
 
     Ok(())
 }
 
 /// # Errors
 /// Returns error if:
+/// - The save is invalid
 /// - Invalid SQL statement is present
 /// - Database update failed
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn update_save(db: State<'_, Database>, name: &str, save: Save) -> DbResult<()> {
-    let conn = db.0.lock();
+pub async fn update_save(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    name: String,
+    save: Save,
+) -> DbResult<()> {
+    save.validate().map_err(DbError::Invalid)?;
+    let history = history.inner().clone();
 
-    conn.prepare_cached("UPDATE save SET last_modified = ?2, data = ?3 WHERE name = ?1")
-        .map_err(|_| DbError::Execution)?
-        .execute((name, Utc::now(), save))
-        .map_err(|_| DbError::Update)?;
+    // The edit is recorded by the same job that writes it, so that no undo can run in between
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
 
-    Ok(())
+        // Data that cannot be decoded is still overwritten, but cannot be restored by undoing
+        let old = get_save_from_name(&*tx, &name).ok();
+
+        write_save(&tx, &name, &save)?;
+        tx.commit().map_err(DbError::Update)?;
+
+        if let Some(old) = old.filter(|old| *old != save) {
+            history.record(&name, old);
+        }
+
+        Ok(())
+    })
+    .await
 }
 
+/// Moves a save to the trash, from which it can be restored until the trash is purged.
+///
 /// # Errors
 /// Returns error if:
 /// - Invalid SQL statement is present
 /// - Database update failed
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn delete_save(db: State<'_, Database>, name: &str) -> DbResult<()> {
-    let conn = db.0.lock();
+pub async fn delete_save(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    name: String,
+) -> DbResult<()> {
+    let name = db
+        .run(move |conn| {
+            conn.prepare_cached("UPDATE save SET deleted = ?2 WHERE name = ?1 AND deleted IS NULL")
+                .map_err(DbError::Execution)?
+                .execute((&name, Utc::now()))
+                .map_err(DbError::Deletion)?;
+
+            Ok(name)
+        })
+        .await?;
 
-    conn.prepare_cached("DELETE FROM save WHERE name = ?1")
-        .map_err(|_| DbError::Execution)?
-        .execute([name])
-        .map_err(|_| DbError::Deletion)?;
+    history.remove(&name);
 
     Ok(())
 }
 
-#[derive(Serialize)]
-struct NamedSave<'cmd> {
-    name: &'cmd str,
-    data: Save,
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NamedSave<'cmd> {
+    pub(crate) name: Cow<'cmd, str>,
+    pub(crate) data: Save,
 }
 
 /// # Errors
@@ -310,21 +1060,146 @@ struct NamedSave<'cmd> {
 /// - Database update failed
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
-pub fn export_save(db: State<'_, Database>, name: &str) -> DbResult<()> {
+pub async fn export_save(db: State<'_, Database>, name: String) -> DbResult<()> {
+    let save = db
+        .run(move |conn| {
+            let data = get_resolved_save(&*conn, &name)?;
+
+            Ok(NamedSave {
+                name: Cow::Owned(name),
+                data,
+            })
+        })
+        .await?;
+    let contents = serde_json::to_vec(&save).map_err(|e| DbError::Export(e.into()))?;
+
     let target_dir = download_dir().ok_or(DbError::NoExportTarget)?;
     let target_name = get_available_name("export", |new_name| {
         !target_dir.join(new_name).with_extension("json").is_file()
     });
-    let target_file = BufWriter::new(
-        File::create(target_dir.join(target_name.as_ref()).with_extension("json"))
-            .map_err(|_| DbError::Export)?,
-    );
-
-    let conn = db.0.lock();
-    let save = NamedSave {
-        name,
-        data: get_save_from_name(conn, name)?,
-    };
 
-    serde_json::to_writer(target_file, &save).map_err(|_| DbError::Export)
+    write_file(
+        target_dir.join(target_name.as_ref()).with_extension("json"),
+        contents,
+    )
+    .await
+}
+
+/// Reads a file on a blocking thread, so that it does not block the async runtime.
+pub(crate) async fn read_file(path: PathBuf) -> DbResult<Vec<u8>> {
+    spawn_blocking(move || fs::read(path))
+        .await
+        .expect("Failed to join file reading thread")
+        .map_err(DbError::ImportRead)
+}
+
+/// Writes a file on a blocking thread, so that it does not block the async runtime.
+pub(crate) async fn write_file(path: PathBuf, contents: Vec<u8>) -> DbResult<()> {
+    spawn_blocking(move || fs::write(path, contents))
+        .await
+        .expect("Failed to join file writing thread")
+        .map_err(DbError::Export)
+}
+
+// Reads and validates a save exported by `export_save`.
+fn read_export(path: &Path) -> DbResult<NamedSave<'static>> {
+    let file = BufReader::new(File::open(path).map_err(DbError::ImportRead)?);
+    let save: NamedSave<'_> =
+        serde_json::from_reader(file).map_err(|e| DbError::ImportFormat(Some(e)))?;
+
+    if save.name.trim().is_empty() {
+        return Err(DbError::ImportFormat(None));
+    }
+    save.data.validate().map_err(DbError::Invalid)?;
+
+    Ok(save)
+}
+
+/// Imports a save exported by `export_save`.
+/// If another save has the same name, the imported save is given a numbered name.
+/// Returns the name of the imported save.
+///
+/// # Errors
+/// Returns error if:
+/// - The file cannot be read
+/// - The file is not a save export
+/// - The save is invalid
+/// - Invalid SQL statement is present
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_save(db: State<'_, Database>, path: PathBuf) -> DbResult<Box<str>> {
+    db.run(move |conn| {
+        let save = read_export(&path)?;
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let name = insert_save(&tx, &save.name, &save.data)?;
+        tx.commit().map_err(DbError::Creation)?;
+
+        Ok(name)
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct ImportResult {
+    path: PathBuf,
+    name: Option<Box<str>>, // Name of the imported save, if the import succeeded
+    error: Option<Box<str>>, // Reason for failure, if the import failed
+}
+
+/// Imports every save export (`.json` file) directly inside a directory.
+/// Files that fail to import are reported without stopping the other imports.
+/// The import can be cancelled with `cancel_operation`, using `operation` as its ID.
+///
+/// # Errors
+/// Returns error if:
+/// - The directory cannot be read
+/// - The operation was cancelled
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_saves(
+    db: State<'_, Database>,
+    dir: PathBuf,
+    operation: OperationId,
+) -> DbResult<Box<[ImportResult]>> {
+    db.run_cancellable(operation, move |conn, cancelled| {
+        let mut paths = read_dir(dir)
+            .map_err(DbError::ImportRead)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && path.extension() == Some("json".as_ref()))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut results = Vec::with_capacity(paths.len());
+
+        for path in paths {
+            // Saves imported before the cancellation are kept
+            cancelled.check()?;
+
+            // Each file is imported in its own transaction, so one failure does not undo others
+            let result = read_export(&path).and_then(|save| {
+                let tx = conn.transaction().map_err(DbError::Execution)?;
+                let name = insert_save(&tx, &save.name, &save.data)?;
+                tx.commit().map_err(DbError::Creation)?;
+                Ok(name)
+            });
+
+            results.push(match result {
+                Ok(name) => ImportResult {
+                    path,
+                    name: Some(name),
+                    error: None,
+                },
+                Err(e) => ImportResult {
+                    path,
+                    name: None,
+                    error: Some(e.to_string().into_boxed_str()),
+                },
+            });
+        }
+
+        Ok(results.into_boxed_slice())
+    })
+    .await
 }
@@ -0,0 +1,167 @@
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+
+const CHARS_DATA: &str = include_str!("../../src/lib/data/chars.json");
+const FACILITIES_DATA: &str = include_str!("../../src/lib/data/facilities.json");
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FacilityKind {
+    Control,
+    Dormitory,
+    Hire,
+    Manufacture,
+    Meeting,
+    Power,
+    Trading,
+    Training,
+    Workshop,
+}
+
+impl FacilityKind {
+    /// Returns the name of the facility kind, as used in serialized data.
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::Control => "control",
+            Self::Dormitory => "dormitory",
+            Self::Hire => "hire",
+            Self::Manufacture => "manufacture",
+            Self::Meeting => "meeting",
+            Self::Power => "power",
+            Self::Trading => "trading",
+            Self::Training => "training",
+            Self::Workshop => "workshop",
+        }
+    }
+
+    /// Parses the name of a facility kind, as returned by `name`.
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "control" => Some(Self::Control),
+            "dormitory" => Some(Self::Dormitory),
+            "hire" => Some(Self::Hire),
+            "manufacture" => Some(Self::Manufacture),
+            "meeting" => Some(Self::Meeting),
+            "power" => Some(Self::Power),
+            "trading" => Some(Self::Trading),
+            "training" => Some(Self::Training),
+            "workshop" => Some(Self::Workshop),
+            _ => None,
+        }
+    }
+
+    /// Base skill IDs are prefixed with the kind of facility they take effect in
+    /// (e.g. `manu_prod_spd[000]` only works in factories).
+    pub(crate) fn from_skill_id(id: &str) -> Option<Self> {
+        match id.split_once('_')?.0 {
+            "control" => Some(Self::Control),
+            "dorm" => Some(Self::Dormitory),
+            "hire" => Some(Self::Hire),
+            "manu" => Some(Self::Manufacture),
+            "meet" => Some(Self::Meeting),
+            "power" => Some(Self::Power),
+            "trade" => Some(Self::Trading),
+            "train" => Some(Self::Training),
+            "workshop" => Some(Self::Workshop),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) type Tier = u8;
+
+// Promotion and level requirements (elite, level) of each operator tier.
+// Every base skill phase in the game data unlocks at one of these thresholds.
+const TIER_THRESHOLDS: [(u8, u8); MAX_TIER as usize + 1] = [(0, 1), (0, 30), (1, 1), (2, 1)];
+
+/// Highest tier that an operator can reach
+pub(crate) const MAX_TIER: Tier = 3;
+
+/// Returns the highest tier reached by an operator at the given promotion and level.
+pub(crate) fn tier_from_progress(elite: u8, level: u8) -> Tier {
+    let reached = TIER_THRESHOLDS
+        .iter()
+        .take_while(|&&threshold| (elite, level) >= threshold)
+        .count();
+
+    Tier::try_from(reached.saturating_sub(1)).expect("Failed to convert tier")
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Operator {
+    name: Box<str>,
+    skills: Box<[Box<[Skill]>]>,
+}
+
+impl Operator {
+    /// Returns the operator's display name in the Global (English) client.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Iterates over the highest unlocked phase of each base skill at the given tier.
+    pub(crate) fn skills_at(&self, tier: Tier) -> impl Iterator<Item = &Skill> {
+        self.skills
+            .iter()
+            .filter_map(move |phases| phases.iter().rev().find(|phase| phase.is_unlocked(tier)))
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Skill {
+    pub(crate) id: Box<str>,
+    elite: u8,
+    level: u8,
+}
+
+impl Skill {
+    fn is_unlocked(&self, tier: Tier) -> bool {
+        (self.elite, self.level) <= TIER_THRESHOLDS[usize::from(tier.min(MAX_TIER))]
+    }
+
+    pub(crate) fn facility(&self) -> Option<FacilityKind> {
+        FacilityKind::from_skill_id(&self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct FacilityData {
+    capacity: Box<[u8]>,
+}
+
+pub struct GameData {
+    ops: HashMap<Box<str>, Operator>,
+    facilities: HashMap<FacilityKind, FacilityData>,
+}
+
+impl GameData {
+    #[must_use]
+    pub fn load() -> Self {
+        Self {
+            ops: serde_json::from_str(CHARS_DATA).expect("Failed to parse operator data"),
+            facilities: serde_json::from_str(FACILITIES_DATA)
+                .expect("Failed to parse facility data"),
+        }
+    }
+
+    pub(crate) fn operator(&self, id: &str) -> Option<&Operator> {
+        self.ops.get(id)
+    }
+
+    /// Returns the ID of the operator with the given display name, ignoring case.
+    pub(crate) fn operator_id(&self, name: &str) -> Option<&str> {
+        self.ops
+            .iter()
+            .find(|(_, op)| op.name.eq_ignore_ascii_case(name.trim()))
+            .map(|(id, _)| &**id)
+    }
+
+    /// Returns the number of operators a facility can hold at the given level.
+    /// Unbuilt facilities (level 0) have no capacity.
+    pub(crate) fn capacity(&self, kind: FacilityKind, level: u8) -> usize {
+        level
+            .checked_sub(1)
+            .and_then(|i| self.facilities.get(&kind)?.capacity.get(usize::from(i)))
+            .map_or(0, |&n| n.into())
+    }
+}
@@ -0,0 +1,472 @@
+use crate::{
+    base::{Save, SaveError},
+    db::{get_elapsed_time, get_free_name, write_save, Database, DbError, DbResult},
+    search,
+};
+use chrono::Utc;
+use parking_lot::Mutex;
+use rusqlite::{Connection, Error as SqlError, Transaction};
+use serde::Serialize;
+use tauri::{Config, State};
+
+/// Result of a health check, which runs when the app starts or switches databases
+#[derive(Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    // Why the database could not be opened, in which case a temporary read-only database
+    // is used instead
+    error: Option<Box<str>>,
+    problems: Box<[Box<str>]>, // Integrity problems that could not be repaired
+    repaired: bool,            // Whether integrity problems were repaired by rebuilding indexes
+    quarantined: Box<[QuarantinedSave]>, // Saves quarantined during this check
+    // Saves written by a newer version of the app, which are left in place until it is updated
+    newer_saves: Box<[Box<str>]>,
+}
+
+/// Report of the health check of the current database
+#[derive(Default)]
+pub struct Health(Mutex<HealthReport>);
+
+impl Health {
+    pub(crate) fn get(&self) -> HealthReport {
+        self.0.lock().clone()
+    }
+
+    pub(crate) fn set(&self, report: HealthReport) {
+        *self.0.lock() = report;
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct QuarantinedSave {
+    id: i64,
+    name: Box<str>,
+    reason: Box<str>,
+    quarantined: f32,
+    revisions: usize, // Number of revisions that the save can be restored from
+}
+
+// Returns the problems found by SQLite's integrity check.
+fn check_integrity(conn: &Connection) -> Result<Vec<Box<str>>, SqlError> {
+    let mut problems = conn
+        .prepare("PRAGMA integrity_check")?
+        .query_and_then([], |row| row.get(0))?
+        .collect::<Result<Vec<Box<str>>, SqlError>>()?;
+    problems.retain(|problem| &**problem != "ok");
+
+    Ok(problems)
+}
+
+// Quarantines every save whose data cannot be decoded, hiding it from other commands like
+// trashed saves. Quarantined saves keep their metadata, tags and revisions, so they can be
+// restored once their data can be read again or from a revision.
+// Returns the quarantined saves, along with the names of saves written by a newer version.
+fn quarantine_saves(
+    conn: &mut Connection,
+) -> Result<(Vec<QuarantinedSave>, Vec<Box<str>>), SqlError> {
+    let tx = conn.transaction()?;
+
+    let saves = tx
+        .prepare("SELECT id, name, data FROM save WHERE deleted IS NULL")?
+        .query_and_then([], |row| {
+            let data: Vec<u8> = row.get("data")?;
+
+            Ok((
+                row.get::<_, i64>("id")?,
+                row.get::<_, Box<str>>("name")?,
+                Save::from_blob(&data).err(),
+            ))
+        })?
+        .collect::<Result<Vec<_>, SqlError>>()?;
+
+    let now = Utc::now();
+    let mut quarantined = Vec::new();
+    let mut newer_saves = Vec::new();
+
+    for (save_id, name, error) in saves {
+        let reason = match error {
+            None => continue,
+            // Saves from a newer version are not corrupted, and can be read once the app is updated
+            Some(SaveError::UnsupportedVersion(_)) => {
+                newer_saves.push(name);
+                continue;
+            }
+            Some(e) => e.to_string(),
+        };
+
+        tx.execute("UPDATE save SET deleted = ?2 WHERE id = ?1", (save_id, now))?;
+        tx.execute(
+            "INSERT INTO quarantine (
+                save_id, quarantined, reason
+            ) VALUES (
+                ?1, ?2, ?3
+            )",
+            (save_id, now, &reason),
+        )?;
+        let id = tx.last_insert_rowid();
+        let revisions = tx.query_row(
+            "SELECT count(*) FROM save_revision WHERE save_id = ?1",
+            [save_id],
+            |row| row.get(0),
+        )?;
+
+        quarantined.push(QuarantinedSave {
+            id,
+            name,
+            reason: reason.into_boxed_str(),
+            quarantined: 0.,
+            revisions,
+        });
+    }
+
+    tx.commit()?;
+
+    Ok((quarantined, newer_saves))
+}
+
+/// Outcome of the integrity check, which runs before the database is migrated,
+/// so that corrupted indexes are repaired before anything else writes to the database
+#[derive(Default)]
+pub(crate) struct Integrity {
+    problems: Vec<Box<str>>, // Problems that could not be repaired
+    repaired: bool,
+}
+
+/// Checks the integrity of the database, rebuilding its indexes if they are corrupted.
+pub(crate) fn repair(conn: &Connection) -> Integrity {
+    let mut repaired = false;
+
+    let result = check_integrity(conn).and_then(|problems| {
+        if problems.is_empty() {
+            return Ok(problems);
+        }
+
+        // Corrupted indexes can be rebuilt from their tables
+        conn.execute_batch("REINDEX")?;
+        let remaining = check_integrity(conn)?;
+        repaired = remaining.is_empty();
+
+        Ok(remaining)
+    });
+
+    let problems = match result {
+        Ok(problems) => problems,
+        Err(e) => vec![format!("The integrity check failed: {e}").into_boxed_str()],
+    };
+
+    Integrity { problems, repaired }
+}
+
+/// Quarantines saves that cannot be decoded, and reports them along with the outcome of
+/// the integrity check.
+pub(crate) fn check(conn: &mut Connection, integrity: Integrity) -> HealthReport {
+    let mut report = HealthReport {
+        repaired: integrity.repaired,
+        ..HealthReport::default()
+    };
+    let mut problems = integrity.problems;
+
+    match quarantine_saves(conn) {
+        Ok((quarantined, newer_saves)) => {
+            report.quarantined = quarantined.into_boxed_slice();
+            report.newer_saves = newer_saves.into_boxed_slice();
+        }
+        Err(e) => problems.push(format!("Saves could not be checked: {e}").into_boxed_str()),
+    }
+
+    report.problems = problems.into_boxed_slice();
+    report
+}
+
+/// Opens the app database and checks its health.
+/// If the database cannot be opened, a temporary read-only database is used instead,
+/// so that edits are refused rather than lost when the app exits.
+/// The reason is given in the report, which the frontend shows when the app starts.
+#[must_use]
+pub fn startup(config: &Config) -> (Database, Health) {
+    let (db, report) = match Database::setup(config) {
+        Ok((db, integrity)) => {
+            let report = db
+                .run_blocking(move |conn| Ok(check(conn, integrity)))
+                .unwrap_or_else(|e| HealthReport {
+                    error: Some(e.to_string().into_boxed_str()),
+                    ..HealthReport::default()
+                });
+            (db, report)
+        }
+        Err(e) => {
+            let db = Database::read_only().expect("Failed to set up temporary database");
+            let report = HealthReport {
+                error: Some(e.to_string().into_boxed_str()),
+                ..HealthReport::default()
+            };
+            (db, report)
+        }
+    };
+
+    (db, Health(Mutex::new(report)))
+}
+
+/// Returns the report of the health check of the current database.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+#[must_use]
+pub fn get_health_report(health: State<'_, Health>) -> HealthReport {
+    health.get()
+}
+
+/// Lists quarantined saves, from most to least recently quarantined.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_quarantine(db: State<'_, Database>) -> DbResult<Box<[QuarantinedSave]>> {
+    db.run(|conn| {
+        let now = Utc::now();
+
+        let query = conn
+            .prepare_cached(
+                "SELECT q.id, s.name, q.reason, q.quarantined, (
+                    SELECT count(*) FROM save_revision r WHERE r.save_id = s.id
+                ) AS revisions FROM quarantine q JOIN save s ON q.save_id = s.id
+                ORDER BY q.quarantined DESC",
+            )
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| {
+                Ok(QuarantinedSave {
+                    id: row.get("id")?,
+                    name: row.get("name")?,
+                    reason: row.get("reason")?,
+                    quarantined: get_elapsed_time(row.get("quarantined")?, now),
+                    revisions: row.get("revisions")?,
+                })
+            })
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[QuarantinedSave]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
+
+// Returns the most recent revision of a save that can be decoded, if any.
+fn get_readable_revision(tx: &Transaction<'_>, save_id: i64) -> DbResult<Option<Save>> {
+    let revisions = tx
+        .prepare_cached(
+            "SELECT data FROM save_revision WHERE save_id = ?1 ORDER BY created DESC, id DESC",
+        )
+        .map_err(DbError::Execution)?
+        .query_and_then([save_id], |row| row.get::<_, Vec<u8>>("data"))
+        .map_err(DbError::Fetching)?
+        .collect::<Result<Vec<_>, SqlError>>()
+        .map_err(DbError::Fetching)?;
+
+    Ok(revisions.iter().find_map(|data| Save::from_blob(data).ok()))
+}
+
+/// Moves a quarantined save back among other saves.
+/// If another save has taken its name in the meantime, the save is given a numbered name.
+/// If its data still cannot be decoded, the save is restored from its most recent revision
+/// that can be, and the undecodable data is kept as a revision.
+/// Returns the name of the restored save.
+///
+/// # Errors
+/// Returns error if:
+/// - Neither the save nor any of its revisions can be decoded
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn restore_quarantined(db: State<'_, Database>, id: i64) -> DbResult<Box<str>> {
+    db.run(move |conn| restore(conn, id)).await
+}
+
+fn restore(conn: &mut Connection, id: i64) -> DbResult<Box<str>> {
+    let tx = conn.transaction().map_err(DbError::Execution)?;
+
+    let (save_id, name, data): (i64, Box<str>, Vec<u8>) = tx
+        .prepare_cached(
+            "SELECT s.id, s.name, s.data FROM quarantine q JOIN save s ON q.save_id = s.id
+            WHERE q.id = ?1",
+        )
+        .map_err(DbError::Execution)?
+        .query_row([id], |row| {
+            Ok((row.get("id")?, row.get("name")?, row.get("data")?))
+        })
+        .map_err(DbError::Fetching)?;
+
+    let (save, recovered) = match Save::from_blob(&data) {
+        Ok(save) => (save, false),
+        Err(e) => match get_readable_revision(&tx, save_id)? {
+            Some(save) => (save, true),
+            None => return Err(DbError::Decoding(e)),
+        },
+    };
+
+    let name = get_free_name(&tx, &name)?;
+
+    tx.prepare_cached("UPDATE save SET name = ?2, deleted = NULL WHERE id = ?1")
+        .map_err(DbError::Execution)?
+        .execute((save_id, &name))
+        .map_err(DbError::Restoration)?;
+    tx.prepare_cached("DELETE FROM quarantine WHERE id = ?1")
+        .map_err(DbError::Execution)?
+        .execute([id])
+        .map_err(DbError::Restoration)?;
+
+    if recovered {
+        write_save(&tx, &name, &save)?;
+    } else {
+        search::index(&tx, save_id, &save).map_err(DbError::Restoration)?;
+    }
+
+    tx.commit().map_err(DbError::Restoration)?;
+
+    Ok(name)
+}
+
+/// Permanently deletes a quarantined save, along with its revisions.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn delete_quarantined(db: State<'_, Database>, id: i64) -> DbResult<()> {
+    db.run(move |conn| {
+        conn.prepare_cached(
+            "DELETE FROM save WHERE id = (SELECT save_id FROM quarantine WHERE id = ?1)",
+        )
+        .map_err(DbError::Execution)?
+        .execute([id])
+        .map_err(DbError::Deletion)?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::migrate;
+
+    // Saves written by a future format version, in an otherwise valid envelope
+    const NEWER_BLOB: &[u8] = b"RIIC\x64\x00";
+
+    fn insert(conn: &Connection, name: &str, data: &[u8]) -> i64 {
+        conn.execute(
+            "INSERT INTO save (
+                name, created, last_modified, data
+            ) VALUES (
+                ?1, ?2, ?2, ?3
+            )",
+            (name, Utc::now(), data),
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn setup() -> (Connection, i64) {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        insert(&conn, "Good", &Save::default().to_blob().unwrap());
+        insert(&conn, "Newer", NEWER_BLOB);
+        let broken = insert(&conn, "Broken", b"");
+
+        (conn, broken)
+    }
+
+    fn add_revision(conn: &Connection, save_id: i64, data: &[u8]) {
+        conn.execute(
+            "INSERT INTO save_revision (
+                save_id, created, data
+            ) VALUES (
+                ?1, ?2, ?3
+            )",
+            (save_id, Utc::now(), data),
+        )
+        .unwrap();
+    }
+
+    fn is_deleted(conn: &Connection, save_id: i64) -> bool {
+        conn.query_row(
+            "SELECT deleted IS NOT NULL FROM save WHERE id = ?1",
+            [save_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn quarantines_only_undecodable_saves() {
+        let (mut conn, broken) = setup();
+        add_revision(&conn, broken, &Save::default().to_blob().unwrap());
+
+        let (quarantined, newer_saves) = quarantine_saves(&mut conn).unwrap();
+
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(&*quarantined[0].name, "Broken");
+        assert_eq!(quarantined[0].revisions, 1);
+        assert_eq!(newer_saves, [Box::from("Newer")]);
+
+        // The quarantined save keeps its row and revisions, hidden like a trashed save
+        assert!(is_deleted(&conn, broken));
+        let revisions: i64 = conn
+            .query_row(
+                "SELECT count(*) FROM save_revision WHERE save_id = ?1",
+                [broken],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(revisions, 1);
+
+        // Saves that are already quarantined are not checked again
+        let (quarantined, _) = quarantine_saves(&mut conn).unwrap();
+        assert!(quarantined.is_empty());
+    }
+
+    #[test]
+    fn restores_from_readable_revision() {
+        let (mut conn, broken) = setup();
+        add_revision(&conn, broken, &Save::default().to_blob().unwrap());
+        let (quarantined, _) = quarantine_saves(&mut conn).unwrap();
+
+        // Another save took the name while the save was quarantined
+        insert(&conn, "Broken", &Save::default().to_blob().unwrap());
+
+        let name = restore(&mut conn, quarantined[0].id).unwrap();
+
+        assert_eq!(&*name, "Broken-1");
+        assert!(!is_deleted(&conn, broken));
+        let data: Vec<u8> = conn
+            .query_row("SELECT data FROM save WHERE id = ?1", [broken], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(Save::from_blob(&data).unwrap() == Save::default());
+        let remaining: i64 = conn
+            .query_row("SELECT count(*) FROM quarantine", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn keeps_unrecoverable_saves_quarantined() {
+        let (mut conn, broken) = setup();
+        let (quarantined, _) = quarantine_saves(&mut conn).unwrap();
+
+        assert!(matches!(
+            restore(&mut conn, quarantined[0].id),
+            Err(DbError::Decoding(_))
+        ));
+        assert!(is_deleted(&conn, broken));
+    }
+}
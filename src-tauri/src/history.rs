@@ -0,0 +1,141 @@
+use crate::{
+    base::Save,
+    db::{get_save_from_name, write_save, Database, DbError, DbResult},
+};
+use ahash::HashMap;
+use parking_lot::Mutex;
+use std::{collections::VecDeque, sync::Arc};
+use tauri::State;
+
+const MAX_UNDO_STEPS: usize = 50; // Maximum number of edits that can be undone per save
+
+#[derive(Default)]
+struct EditHistory {
+    undo: VecDeque<Save>, // Previous versions of the save, from oldest to newest
+    redo: Vec<Save>,      // Undone versions of the save, from newest to oldest
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Undo,
+    Redo,
+}
+
+impl EditHistory {
+    // Returns the version of the save that the step would restore.
+    fn next(&self, step: Step) -> Option<&Save> {
+        match step {
+            Step::Undo => self.undo.back(),
+            Step::Redo => self.redo.last(),
+        }
+    }
+
+    // Takes the version restored by the step off its stack, putting `current` on the other one.
+    fn apply(&mut self, step: Step, current: Save) -> Option<Save> {
+        match step {
+            Step::Undo => {
+                self.redo.push(current);
+                self.undo.pop_back()
+            }
+            Step::Redo => {
+                self.undo.push_back(current);
+                self.redo.pop()
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks of saves edited during this session.
+/// Unlike revisions, this history is not persisted.
+/// Clones share the same history, so it can be updated from database operations.
+#[derive(Clone, Default)]
+pub struct History(Arc<Mutex<HashMap<Box<str>, EditHistory>>>);
+
+impl History {
+    /// Records an edit that replaced `old` with new data, clearing undone edits.
+    pub(crate) fn record(&self, name: &str, old: Save) {
+        let mut saves = self.0.lock();
+        let history = saves.entry(name.into()).or_default();
+
+        if history.undo.len() == MAX_UNDO_STEPS {
+            history.undo.pop_front();
+        }
+        history.undo.push_back(old);
+        history.redo.clear();
+    }
+
+    pub(crate) fn rename(&self, old: &str, new: &str) {
+        let mut saves = self.0.lock();
+
+        if let Some(history) = saves.remove(old) {
+            saves.insert(new.into(), history);
+        }
+    }
+
+    pub(crate) fn remove(&self, name: &str) {
+        self.0.lock().remove(name);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().clear();
+    }
+}
+
+// Moves one version of a save from one stack to the other, writing it to the database.
+async fn step(db: &Database, history: &History, name: String, step: Step) -> DbResult<Save> {
+    let history = history.clone();
+
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let current = get_save_from_name(&*tx, &name)?;
+
+        // The history stays locked until the write is committed, and is only changed afterwards,
+        // so a failed write cannot leave it out of step with the database
+        let mut saves = history.0.lock();
+        let entry = saves.get_mut(name.as_str()).ok_or(DbError::NoHistory)?;
+        let save = entry.next(step).ok_or(DbError::NoHistory)?;
+
+        write_save(&tx, &name, save)?;
+        tx.commit().map_err(DbError::Update)?;
+
+        entry.apply(step, current).ok_or(DbError::NoHistory)
+    })
+    .await
+}
+
+/// Reverts the last edit of a save made during this session and returns the reverted data.
+///
+/// # Errors
+/// Returns error if:
+/// - There is no edit to undo
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn undo_save(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    name: String,
+) -> DbResult<Save> {
+    step(&db, &history, name, Step::Undo).await
+}
+
+/// Reapplies the last undone edit of a save and returns the reapplied data.
+///
+/// # Errors
+/// Returns error if:
+/// - There is no edit to redo
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn redo_save(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    name: String,
+) -> DbResult<Save> {
+    step(&db, &history, name, Step::Redo).await
+}
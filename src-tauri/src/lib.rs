@@ -1,7 +1,42 @@
 #![allow(clippy::missing_panics_doc)]
 
+// Async commands that take `State` generate an underscore-prefixed binding next to the command,
+// which cannot be allowed on the command itself, so the lint is allowed in their modules instead
+#[allow(clippy::used_underscore_binding)]
+pub mod analysis;
+#[allow(clippy::used_underscore_binding)]
+pub mod archive;
+#[allow(clippy::used_underscore_binding)]
+pub mod backup;
 mod base;
+#[allow(clippy::used_underscore_binding)]
 pub mod db;
+pub mod gamedata;
+#[allow(clippy::used_underscore_binding)]
+pub mod health;
+#[allow(clippy::used_underscore_binding)]
+pub mod history;
+#[allow(clippy::used_underscore_binding)]
+pub mod location;
+#[allow(clippy::used_underscore_binding)]
+pub mod maa;
+#[allow(clippy::used_underscore_binding)]
+pub mod metadata;
+#[allow(clippy::used_underscore_binding)]
+pub mod revision;
+#[allow(clippy::used_underscore_binding)]
+pub mod roster;
+#[allow(clippy::used_underscore_binding)]
+pub mod schedule;
+mod schema;
+#[allow(clippy::used_underscore_binding)]
+pub mod search;
+#[allow(clippy::used_underscore_binding)]
+pub mod share;
+#[allow(clippy::used_underscore_binding)]
+pub mod template;
+#[allow(clippy::used_underscore_binding)]
+pub mod trash;
 pub mod window;
 
 const MAX_SAVE_SIZE: usize = 1_000_000;
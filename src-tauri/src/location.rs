@@ -0,0 +1,238 @@
+use crate::{
+    db::{open_connection, Database, DbError, DbResult, SetupError},
+    health::{self, Health, HealthReport},
+    history::History,
+};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::{
+    env,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tauri::{api::path::app_data_dir, utils::platform::current_exe, Config, State};
+
+const DB_PATH_ARG: &str = "--db"; // Command-line option overriding the database path
+const DB_PATH_VAR: &str = "RIIC_DB_PATH"; // Environment variable overriding the database path
+const PORTABLE_MARKER: &str = "portable"; // File next to the binary that enables portable mode
+const DEFAULT_DB_NAME: &str = "data";
+const DB_EXTENSION: &str = "db";
+const CHOICE_FILE: &str = "database"; // File in the database directory naming the chosen database
+
+fn get_path_override() -> Option<PathBuf> {
+    let mut args = env::args_os().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == DB_PATH_ARG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--db=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    env::var_os(DB_PATH_VAR).map(PathBuf::from)
+}
+
+/// Determines where the database is stored, creating its directory if needed.
+/// In order of precedence, the database is stored:
+/// - At the path given by the `--db` option or the `RIIC_DB_PATH` environment variable
+/// - Next to the binary, in portable mode
+/// - In the platform's app data directory
+///
+/// Portable mode is enabled by a `portable` file next to the binary. Installs that predate
+/// this setting already keep their database next to the binary, so they stay in portable mode.
+/// Unless the path is overridden, the database last switched to is used if it still exists.
+pub(crate) fn resolve(config: &Config) -> Result<PathBuf, SetupError> {
+    let db_file = Path::new(DEFAULT_DB_NAME).with_extension(DB_EXTENSION);
+
+    let db_path = if let Some(path) = get_path_override() {
+        path
+    } else {
+        let exe_dir = current_exe()
+            .expect("Failed to get the currently-running binary path")
+            .with_file_name("");
+
+        let db_dir = if exe_dir.join(PORTABLE_MARKER).is_file() || exe_dir.join(&db_file).is_file()
+        {
+            exe_dir
+        } else {
+            app_data_dir(config).ok_or(SetupError::NoDataDir)?
+        };
+
+        get_chosen_path(&db_dir).unwrap_or_else(|| db_dir.join(db_file))
+    };
+
+    if let Some(dir) = db_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_dir_all(dir).map_err(SetupError::DataDir)?;
+    }
+
+    Ok(db_path)
+}
+
+// Returns the path of the current database file.
+// The temporary database used when the database cannot be opened has no file.
+fn get_current_path(conn: &Connection) -> DbResult<PathBuf> {
+    conn.path()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .ok_or(DbError::NoDatabaseFile)
+}
+
+fn get_db_name(path: &Path) -> Option<Box<str>> {
+    if path.extension() != Some(DB_EXTENSION.as_ref()) {
+        return None;
+    }
+
+    path.file_stem()?.to_str().map(Into::into)
+}
+
+// Database names become file names, so they are limited to characters that are safe on every
+// platform and cannot escape the database directory. Windows drops trailing spaces from
+// file names, so names cannot start or end with one.
+fn is_valid_db_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.trim() == name
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+}
+
+// Windows reserves these device names, even with an extension (e.g. `NUL.db`).
+fn is_reserved_db_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+
+    if matches!(name.as_str(), "CON" | "PRN" | "AUX" | "NUL") {
+        return true;
+    }
+
+    let Some(port) = name
+        .strip_prefix("COM")
+        .or_else(|| name.strip_prefix("LPT"))
+    else {
+        return false;
+    };
+    let mut chars = port.chars();
+
+    matches!(
+        (chars.next(), chars.next()),
+        (Some('0'..='9' | '\u{b9}' | '\u{b2}' | '\u{b3}'), None)
+    )
+}
+
+fn check_db_name(name: &str) -> DbResult<()> {
+    if !is_valid_db_name(name) {
+        return Err(DbError::DatabaseName);
+    }
+    if is_reserved_db_name(name) {
+        return Err(DbError::ReservedDatabaseName);
+    }
+
+    Ok(())
+}
+
+// Returns the path of the database last switched to in a directory, if it still exists.
+fn get_chosen_path(dir: &Path) -> Option<PathBuf> {
+    let name = read_to_string(dir.join(CHOICE_FILE)).ok()?;
+    check_db_name(&name).ok()?;
+
+    let path = dir.join(name).with_extension(DB_EXTENSION);
+    if path.is_file() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+#[derive(Serialize)]
+pub struct DatabaseData {
+    name: Box<str>,
+    current: bool,
+}
+
+/// Lists the databases stored alongside the current database, in alphabetical order.
+///
+/// # Errors
+/// Returns error if:
+/// - The app is using a temporary database, which has no directory
+/// - The database directory cannot be read
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_databases(db: State<'_, Database>) -> DbResult<Box<[DatabaseData]>> {
+    let current_path = db.run(|conn| get_current_path(conn)).await?;
+    let current = get_db_name(&current_path);
+
+    let mut names = read_dir(current_path.with_file_name(""))
+        .map_err(DbError::DatabaseList)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter_map(|path| get_db_name(&path))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    let databases = names
+        .into_iter()
+        .map(|name| DatabaseData {
+            current: current.as_ref() == Some(&name),
+            name,
+        })
+        .collect();
+
+    Ok(databases)
+}
+
+/// Switches to another database stored alongside the current database,
+/// creating it if it does not exist. The new database is health-checked like at startup,
+/// and is opened again the next time the app starts.
+/// Returns the report of the health check of the database switched to.
+///
+/// # Errors
+/// Returns error if:
+/// - The database name is invalid or reserved
+/// - The app is using a temporary database, which has no directory
+/// - The current database cannot be optimized
+/// - The database cannot be opened
+/// - The choice of database cannot be saved
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn switch_database(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    health: State<'_, Health>,
+    name: String,
+) -> DbResult<HealthReport> {
+    check_db_name(&name)?;
+
+    let report = db
+        .run(move |conn| {
+            let current_path = get_current_path(conn)?;
+            let new_path = current_path
+                .with_file_name(&name)
+                .with_extension(DB_EXTENSION);
+            if new_path == current_path {
+                return Ok(None);
+            }
+
+            // The current database is optimized as it would be when the app exits
+            conn.execute("PRAGMA optimize;", ())
+                .map_err(DbError::Execution)?;
+
+            let (new_conn, integrity) =
+                open_connection(&new_path).map_err(DbError::DatabaseOpen)?;
+            write(new_path.with_file_name(CHOICE_FILE), &name).map_err(DbError::DatabaseChoice)?;
+
+            *conn = new_conn;
+            Ok(Some(health::check(conn, integrity)))
+        })
+        .await?;
+
+    let Some(report) = report else {
+        return Ok(health.get());
+    };
+
+    // Undo history refers to saves in the previous database
+    history.clear();
+    health.set(report.clone());
+
+    Ok(report)
+}
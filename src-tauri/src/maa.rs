@@ -0,0 +1,637 @@
+use crate::{
+    base::{Layout, Minutes, Operator, Room, Save, ShiftCount, MAX_SHIFT_DURATION},
+    db::{insert_save, read_file, write_file, Database, DbError, DbResult},
+    gamedata::{FacilityKind, GameData},
+    roster::get_resolved_save,
+};
+use ahash::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+use tauri::State;
+
+const MINUTES_PER_DAY: u32 = 1440;
+
+// MAA (MaaAssistantArknights) custom infrastructure plan
+#[derive(Serialize)]
+struct Plan<'a> {
+    title: &'a str,
+    description: &'static str,
+    plans: Vec<PlanShift<'a>>,
+}
+
+// Operators assigned during one shift
+#[derive(Serialize)]
+struct PlanShift<'a> {
+    name: String,
+    description: String,
+    // Daily times (HH:MM, both inclusive) at which the shift is active.
+    // Times that cross midnight are split into two periods, since MAA does not wrap them.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    period: Vec<[String; 2]>,
+    duration: Minutes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    drones: Option<PlanDrones>,
+    rooms: BTreeMap<&'static str, Vec<PlanRoom<'a>>>,
+}
+
+#[derive(Serialize)]
+struct PlanDrones {
+    enable: bool,
+    room: &'static str,
+    index: usize, // 1-based index of the room among rooms of its kind
+    order: &'static str,
+}
+
+#[derive(Serialize)]
+struct PlanRoom<'a> {
+    operators: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    product: Option<&'static str>,
+    autofill: bool,
+}
+
+/// Parts of a save that could not be represented in an exported plan
+#[derive(Serialize)]
+pub struct ExportReport {
+    problems: Box<[ExportProblem]>,
+}
+
+#[derive(PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum ExportProblem {
+    // An operator missing from the game data, who was left out of the plan
+    UnknownChar { char: Operator },
+    // An operator assigned to several rooms in one shift, who was only kept in the first room
+    DuplicateChar { char: Operator, shift: ShiftCount },
+    // A room that uses drones in a shift where an earlier room already does.
+    // Plans can only send drones to one room per shift.
+    ExtraBoost { room: Room, shift: ShiftCount },
+    // The rotation does not repeat a whole number of times per day,
+    // so shifts cannot be given fixed daily times
+    IrregularRotation,
+}
+
+// Returns the MAA name of a product.
+fn product_name(product: &str) -> Option<&'static str> {
+    match product {
+        "lmd" => Some("LMD"),
+        "orundum" => Some("Orundum"),
+        "exp200" | "exp400" | "exp1000" => Some("Battle Record"),
+        "gold" => Some("Pure Gold"),
+        "shard" => Some("Originium Shard"),
+        _ => None,
+    }
+}
+
+fn format_time(minutes: u32) -> String {
+    let minutes = minutes % MINUTES_PER_DAY;
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+// Returns the daily times at which each shift is active, or `None` if the rotation
+// does not fit a whole number of times into a day.
+// The rotation starts at its start time (in server time), or at midnight if it has none.
+fn get_periods(save: &Save) -> Option<Vec<Vec<[String; 2]>>> {
+    let durations: Vec<u32> = (0..save.max_shift)
+        .map(|shift| save.shift_duration(shift).into())
+        .collect();
+    let total: u32 = durations.iter().sum();
+    if total == 0 || MINUTES_PER_DAY % total != 0 {
+        return None;
+    }
+
+    let start = save.clock.map_or(0, |clock| {
+        let seconds = (clock.start + clock.server.utc_offset()).rem_euclid(86_400);
+        u32::try_from(seconds / 60).expect("Failed to convert start time to u32")
+    });
+
+    let mut offset = 0;
+    let periods = durations
+        .into_iter()
+        .map(|duration| {
+            let periods = (0..MINUTES_PER_DAY / total)
+                .filter(|_| duration > 0)
+                .flat_map(|i| {
+                    let begin = (start + i * total + offset) % MINUTES_PER_DAY;
+                    let end = begin + duration - 1;
+
+                    if end < MINUTES_PER_DAY {
+                        vec![[format_time(begin), format_time(end)]]
+                    } else {
+                        vec![
+                            [format_time(begin), format_time(MINUTES_PER_DAY - 1)],
+                            [format_time(0), format_time(end)],
+                        ]
+                    }
+                })
+                .collect();
+            offset += duration;
+            periods
+        })
+        .collect();
+
+    Some(periods)
+}
+
+fn to_plan<'a>(
+    name: &'a str,
+    save: &Save,
+    data: &'a GameData,
+    problems: &mut Vec<ExportProblem>,
+) -> Plan<'a> {
+    let rooms = save.layout.staffed_rooms();
+
+    let periods = get_periods(save);
+    if periods.is_none() {
+        problems.push(ExportProblem::IrregularRotation);
+    }
+
+    let plans = (0..save.max_shift)
+        .map(|shift| {
+            let mut plan = PlanShift {
+                name: format!("Shift {}", shift + 1),
+                description: format!("Shift {} of {}", shift + 1, save.max_shift),
+                period: periods
+                    .as_ref()
+                    .map(|periods| periods[usize::from(shift)].clone())
+                    .unwrap_or_default(),
+                duration: save.shift_duration(shift),
+                drones: None,
+                rooms: BTreeMap::new(),
+            };
+            let mut assigned = Vec::new();
+
+            for room in &rooms {
+                let capacity = data.capacity(room.kind(), room.level);
+                let mut operators = Vec::new();
+
+                for char in room.slots(shift, capacity).flatten() {
+                    let Some(op) = data.operator(char) else {
+                        let problem = ExportProblem::UnknownChar { char: char.into() };
+                        if !problems.contains(&problem) {
+                            problems.push(problem);
+                        }
+                        continue;
+                    };
+
+                    if assigned.contains(&char) {
+                        problems.push(ExportProblem::DuplicateChar {
+                            char: char.into(),
+                            shift,
+                        });
+                        continue;
+                    }
+                    assigned.push(char);
+                    operators.push(op.name());
+                }
+
+                let entries = plan.rooms.entry(room.kind().name()).or_default();
+                entries.push(PlanRoom {
+                    operators,
+                    product: room.product(shift).and_then(product_name),
+                    autofill: false,
+                });
+
+                if room.is_boosted(shift) {
+                    if plan.drones.is_some() {
+                        problems.push(ExportProblem::ExtraBoost {
+                            room: room.room,
+                            shift,
+                        });
+                    } else {
+                        plan.drones = Some(PlanDrones {
+                            enable: true,
+                            room: room.kind().name(),
+                            index: entries.len(),
+                            order: "post",
+                        });
+                    }
+                }
+            }
+
+            plan
+        })
+        .collect();
+
+    Plan {
+        title: name,
+        description: "Exported from RIIC",
+        plans,
+    }
+}
+
+/// Writes a save as an MAA custom infrastructure plan to `path`, overwriting any existing file.
+/// Operators are given by their Global (English) names, and each shift becomes one plan.
+/// Shift times are in server time when the save has a rotation start time.
+/// Returns the parts of the save that could not be represented in the plan.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - The plan cannot be written
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn export_maa(
+    db: State<'_, Database>,
+    data: State<'_, GameData>,
+    name: String,
+    path: PathBuf,
+) -> DbResult<ExportReport> {
+    let save = {
+        let name = name.clone();
+        db.run(move |conn| get_resolved_save(&*conn, &name)).await?
+    };
+
+    let mut problems = Vec::new();
+    let plan = to_plan(&name, &save, &data, &mut problems);
+
+    let contents = serde_json::to_vec_pretty(&plan).map_err(|e| DbError::Export(e.into()))?;
+    write_file(path, contents).await?;
+
+    Ok(ExportReport {
+        problems: problems.into_boxed_slice(),
+    })
+}
+
+#[derive(Deserialize)]
+struct ImportedPlan {
+    #[serde(default)]
+    title: Option<String>,
+    plans: Vec<ImportedShift>,
+}
+
+#[derive(Deserialize)]
+struct ImportedShift {
+    #[serde(default)]
+    period: Vec<[String; 2]>,
+    #[serde(default)]
+    duration: Option<Minutes>,
+    #[serde(default)]
+    rooms: HashMap<String, Vec<ImportedRoom>>,
+}
+
+#[derive(Deserialize)]
+struct ImportedRoom {
+    #[serde(default)]
+    operators: Vec<String>,
+    #[serde(default)]
+    product: Option<String>,
+}
+
+/// Result of importing a plan, including the parts that could not be imported
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    name: Box<str>,                   // Name of the new save
+    unmatched_names: Box<[Box<str>]>, // Operator names that match no operator, for fixing by hand
+    problems: Box<[ImportProblem]>,
+}
+
+#[derive(PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum ImportProblem {
+    // A kind of room that operators cannot be assigned to in a save
+    UnknownRoom {
+        room: Box<str>,
+    },
+    // A room beyond the number of rooms of its kind that a base can have
+    ExtraRoom {
+        room: FacilityKind,
+        index: usize,
+    },
+    // Operators beyond the capacity of a room, who were left out
+    ExtraChars {
+        room: FacilityKind,
+        index: usize,
+        shift: ShiftCount,
+    },
+    // A product that the room cannot make, which was left out
+    UnknownProduct {
+        product: Box<str>,
+    },
+}
+
+// Returns the save name of an MAA product.
+// Battle records are assumed to be the highest tier, which upgraded factories make.
+fn import_product_name(product: &str) -> Option<&'static str> {
+    match product {
+        "LMD" => Some("lmd"),
+        "Orundum" => Some("orundum"),
+        "Battle Record" => Some("exp1000"),
+        "Pure Gold" => Some("gold"),
+        "Originium Shard" => Some("shard"),
+        _ => None,
+    }
+}
+
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+
+    (hours < 24 && minutes < 60).then(|| hours * 60 + minutes)
+}
+
+// Returns the duration of a shift from its duration, or else from its first period.
+// Periods that continue past midnight are split in two (as by `get_periods`),
+// so a period ending at midnight is joined with one starting at midnight.
+fn get_duration(shift: &ImportedShift) -> Option<Minutes> {
+    if let Some(duration) = shift.duration {
+        return Some(duration);
+    }
+
+    let periods = (shift.period.iter())
+        .map(|[start, end]| Some((parse_time(start)?, parse_time(end)?)))
+        .collect::<Option<Vec<_>>>()?;
+    // Periods include their end minute, and can wrap around midnight
+    let length = |(start, end): (u32, u32)| (end + MINUTES_PER_DAY - start) % MINUTES_PER_DAY + 1;
+
+    let (&first, rest) = periods.split_first()?;
+    let mut minutes = length(first);
+    let mut end = first.1;
+    for &period in rest {
+        if end != MINUTES_PER_DAY - 1 || period.0 != 0 {
+            break;
+        }
+        minutes += length(period);
+        end = period.1;
+    }
+
+    // The first period can also continue the last one, if it is listed last
+    if let [.., last] = rest {
+        if first.0 == 0 && last.1 == MINUTES_PER_DAY - 1 && end != MINUTES_PER_DAY - 1 {
+            minutes += length(*last);
+        }
+    }
+
+    Minutes::try_from(minutes).ok()
+}
+
+// Returns the duration of each shift of a plan.
+// Shifts without a duration or period share the rest of the day evenly.
+fn get_durations(plan: &ImportedPlan) -> Vec<Minutes> {
+    let durations: Vec<Option<Minutes>> = plan.plans.iter().map(get_duration).collect();
+    let unknown = durations
+        .iter()
+        .filter(|duration| duration.is_none())
+        .count();
+    let known: u32 = durations
+        .iter()
+        .flatten()
+        .map(|&duration| u32::from(duration))
+        .sum();
+    let default_duration = u32::try_from(unknown)
+        .ok()
+        .filter(|&unknown| unknown > 0)
+        .map_or(0, |unknown| MINUTES_PER_DAY.saturating_sub(known) / unknown)
+        .clamp(1, MAX_SHIFT_DURATION.into());
+    let default_duration =
+        Minutes::try_from(default_duration).expect("Failed to convert shift duration");
+    durations
+        .into_iter()
+        .map(|duration| duration.unwrap_or(default_duration))
+        .collect()
+}
+
+fn from_plan(plan: &ImportedPlan, data: &GameData, report: &mut ImportReport) -> DbResult<Save> {
+    let shift_count = ShiftCount::try_from(plan.plans.len())
+        .ok()
+        .filter(|&count| count > 0)
+        .ok_or(DbError::ImportFormat(None))?;
+
+    let durations = get_durations(plan);
+
+    let count_rooms = |kind: &str| {
+        (plan.plans.iter())
+            .map(|shift| shift.rooms.get(kind).map_or(0, Vec::len))
+            .max()
+            .unwrap_or(0)
+    };
+    // Rooms beyond the number that a base can have are reported as extra rooms below
+    let layout = Layout::upgraded(
+        count_rooms(FacilityKind::Trading.name()),
+        count_rooms(FacilityKind::Manufacture.name()),
+        count_rooms(FacilityKind::Power.name()),
+    );
+    let mut save = Save::with_shifts(layout, durations);
+
+    let mut problems = Vec::new();
+    let mut unmatched: Vec<Box<str>> = Vec::new();
+    let levels: HashMap<(FacilityKind, usize), u8> = save
+        .layout
+        .staffed_rooms()
+        .iter()
+        .map(|room| ((room.kind(), room.index()), room.level))
+        .collect();
+
+    for (shift, imported) in (0..shift_count).zip(&plan.plans) {
+        for (kind_name, rooms) in &imported.rooms {
+            let Some(kind) = FacilityKind::from_name(kind_name) else {
+                let problem = ImportProblem::UnknownRoom {
+                    room: kind_name.as_str().into(),
+                };
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+                continue;
+            };
+
+            for (index, room) in rooms.iter().enumerate() {
+                let Some(&level) = levels.get(&(kind, index)) else {
+                    let problem = ImportProblem::ExtraRoom { room: kind, index };
+                    if !problems.contains(&problem) {
+                        problems.push(problem);
+                    }
+                    continue;
+                };
+                let capacity = data.capacity(kind, level);
+
+                let chars: Vec<&str> = (room.operators.iter())
+                    .filter_map(|name| {
+                        let id = data.operator_id(name);
+                        if id.is_none() && !unmatched.iter().any(|n| **n == *name) {
+                            unmatched.push(name.as_str().into());
+                        }
+                        id
+                    })
+                    .collect();
+                for (i, char) in chars.into_iter().enumerate() {
+                    if i == capacity {
+                        problems.push(ImportProblem::ExtraChars {
+                            room: kind,
+                            index,
+                            shift,
+                        });
+                        break;
+                    }
+                    save.layout
+                        .assign(kind, index, usize::from(shift) * capacity + i, char.into());
+                }
+
+                if let Some(product) = &room.product {
+                    let is_set = import_product_name(product).map_or(false, |name| {
+                        save.layout.set_product(kind, index, shift, name)
+                    });
+                    if !is_set {
+                        problems.push(ImportProblem::UnknownProduct {
+                            product: product.as_str().into(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    report.unmatched_names = unmatched.into_boxed_slice();
+    report.problems = problems.into_boxed_slice();
+
+    Ok(save)
+}
+
+/// Creates a save from an MAA custom infrastructure plan, with one shift per plan.
+/// Operators are matched by their Global (English) names, and rooms are fully upgraded.
+/// Trading posts, factories and power plants beyond the 9 that a base can have are left out.
+/// Drone usage is not imported.
+/// If another save has the plan's title as its name, the save is given a numbered name.
+/// Returns the name of the new save, along with the parts of the plan that could not be imported.
+///
+/// # Errors
+/// Returns error if:
+/// - The file cannot be read
+/// - The file is not an MAA infrastructure plan, or has no shifts
+/// - The save is invalid
+/// - Invalid SQL statement is present
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_maa(
+    db: State<'_, Database>,
+    data: State<'_, GameData>,
+    path: PathBuf,
+) -> DbResult<ImportReport> {
+    let contents = read_file(path).await?;
+    let plan: ImportedPlan =
+        serde_json::from_slice(&contents).map_err(|e| DbError::ImportFormat(Some(e)))?;
+
+    let mut report = ImportReport {
+        name: Box::default(),
+        unmatched_names: Box::default(),
+        problems: Box::default(),
+    };
+    let save = from_plan(&plan, &data, &mut report)?;
+    save.validate().map_err(DbError::Invalid)?;
+
+    let name = match plan.title.as_deref().map(str::trim) {
+        Some(title) if !title.is_empty() => title.to_owned(),
+        _ => "MAA plan".to_owned(),
+    };
+
+    report.name = db
+        .run(move |conn| {
+            let tx = conn.transaction().map_err(DbError::Execution)?;
+
+            let name = insert_save(&tx, &name, &save)?;
+            tx.commit().map_err(DbError::Creation)?;
+
+            Ok(name)
+        })
+        .await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::{Clock, Server};
+
+    fn sample_save() -> Save {
+        let mut save = Save::with_shifts(Layout::upgraded(2, 4, 3), vec![480, 480, 480]);
+        save.layout
+            .assign(FacilityKind::Trading, 0, 0, "char_102_texas".into());
+        save.layout
+            .assign(FacilityKind::Manufacture, 1, 3, "char_017_huang".into());
+        save.layout.set_product(FacilityKind::Trading, 0, 0, "lmd");
+        save.layout
+            .set_product(FacilityKind::Manufacture, 1, 1, "exp1000");
+        save
+    }
+
+    #[test]
+    fn round_trips_plan() {
+        let data = GameData::load();
+        let save = sample_save();
+
+        let mut export_problems = Vec::new();
+        let plan = to_plan("Plan", &save, &data, &mut export_problems);
+        let contents = serde_json::to_vec(&plan).unwrap();
+        assert!(export_problems.is_empty());
+
+        let mut report = ImportReport {
+            name: Box::default(),
+            unmatched_names: Box::default(),
+            problems: Box::default(),
+        };
+        let imported: ImportedPlan = serde_json::from_slice(&contents).unwrap();
+        let imported = from_plan(&imported, &data, &mut report).unwrap();
+
+        assert!(report.unmatched_names.is_empty());
+        assert!(report.problems.is_empty());
+        assert!(imported.layout == save.layout);
+        assert_eq!(imported.max_shift, save.max_shift);
+        for shift in 0..save.max_shift {
+            assert_eq!(imported.shift_duration(shift), save.shift_duration(shift));
+        }
+    }
+
+    #[test]
+    fn round_trips_plan_without_durations() {
+        let data = GameData::load();
+        let mut save = sample_save();
+        // 22:00 in US server time, so that the first shift continues past midnight
+        save.clock = Some(Clock {
+            start: 5 * 3600,
+            server: Server::US,
+        });
+
+        let mut export_problems = Vec::new();
+        let plan = to_plan("Plan", &save, &data, &mut export_problems);
+        let mut contents = serde_json::to_value(&plan).unwrap();
+        for shift in contents["plans"].as_array_mut().unwrap() {
+            shift.as_object_mut().unwrap().remove("duration");
+        }
+
+        let mut report = ImportReport {
+            name: Box::default(),
+            unmatched_names: Box::default(),
+            problems: Box::default(),
+        };
+        let imported: ImportedPlan = serde_json::from_value(contents).unwrap();
+        let imported = from_plan(&imported, &data, &mut report).unwrap();
+
+        for shift in 0..save.max_shift {
+            assert_eq!(imported.shift_duration(shift), save.shift_duration(shift));
+        }
+    }
+
+    #[test]
+    fn splits_periods_at_midnight() {
+        let mut save = sample_save();
+        // 22:00 in US server time
+        save.clock = Some(Clock {
+            start: 5 * 3600,
+            server: Server::US,
+        });
+
+        let periods = get_periods(&save).unwrap();
+
+        let format = |periods: &[[String; 2]]| {
+            (periods.iter())
+                .map(|[begin, end]| format!("{begin}-{end}"))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(format(&periods[0]), ["22:00-23:59", "00:00-05:59"]);
+        assert_eq!(format(&periods[1]), ["06:00-13:59"]);
+        assert_eq!(format(&periods[2]), ["14:00-21:59"]);
+    }
+}
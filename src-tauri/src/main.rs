@@ -3,25 +3,79 @@
     windows_subsystem = "windows"
 )]
 
-use riic_engine::{db, open, window};
+use riic_engine::{
+    analysis, archive, backup, db, gamedata, health, history, location, maa, metadata, open,
+    revision, roster, schedule, search, share, template, trash, window,
+};
 use tauri::{generate_context, generate_handler, Builder, Manager, RunEvent};
 
 fn main() {
+    let context = generate_context!();
+    let (database, health) = health::startup(context.config());
+
     Builder::new()
-        .manage(db::Database::setup().expect("Failed to set up app database"))
+        .manage(database)
+        .manage(health)
+        .manage(history::History::default())
+        .manage(gamedata::GameData::load())
         .invoke_handler(generate_handler![
             db::fetch_saves,
             db::create_save,
+            db::duplicate_save,
             db::get_save,
+            db::check_saves,
+            db::cancel_operation,
+            health::get_health_report,
+            health::list_quarantine,
+            health::restore_quarantined,
+            health::delete_quarantined,
             db::rename_save,
             db::update_save,
             db::delete_save,
+            trash::list_trash,
+            trash::restore_save,
+            trash::empty_trash,
+            trash::get_trash_retention,
+            trash::set_trash_retention,
             db::export_save,
+            db::import_save,
+            db::import_saves,
+            share::create_share_code,
+            share::import_share_code,
+            archive::export_all,
+            maa::export_maa,
+            maa::import_maa,
+            roster::list_roster,
+            roster::set_roster_char,
+            roster::delete_roster_char,
+            roster::find_roster_usage,
+            roster::import_roster,
+            backup::backup_database,
+            backup::restore_database,
+            location::list_databases,
+            location::switch_database,
+            template::fetch_templates,
+            template::create_save_from_template,
+            template::create_template,
+            template::delete_template,
+            metadata::set_description,
+            metadata::set_folder,
+            metadata::set_tags,
+            metadata::fetch_tags,
+            metadata::fetch_folders,
+            search::search_saves,
+            history::undo_save,
+            history::redo_save,
+            revision::list_revisions,
+            revision::get_revision,
+            revision::restore_revision,
+            analysis::analyze_save,
+            schedule::get_schedule,
             open::open,
             window::show_window,
             window::rename_window
         ])
-        .build(generate_context!())
+        .build(context)
         .expect("An error occurred while building the application.")
         .run(|app, event| {
             if let RunEvent::Exit = event {
@@ -0,0 +1,158 @@
+use crate::db::{Database, DbError, DbResult};
+use rusqlite::Error as SqlError;
+use std::collections::BTreeSet;
+use tauri::State;
+
+/// Sets the description of a save.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_description(
+    db: State<'_, Database>,
+    name: String,
+    description: String,
+) -> DbResult<()> {
+    db.run(move |conn| {
+        conn.prepare_cached("UPDATE save SET description = ?2 WHERE name = ?1 AND deleted IS NULL")
+            .map_err(DbError::Execution)?
+            .execute([name.as_str(), description.trim()])
+            .map_err(DbError::Update)?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Moves a save into a folder, or out of any folder if `folder` is empty or missing.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_folder(
+    db: State<'_, Database>,
+    name: String,
+    folder: Option<String>,
+) -> DbResult<()> {
+    db.run(move |conn| {
+        let folder = folder
+            .as_deref()
+            .map(str::trim)
+            .filter(|folder| !folder.is_empty());
+
+        conn.prepare_cached("UPDATE save SET folder = ?2 WHERE name = ?1 AND deleted IS NULL")
+            .map_err(DbError::Execution)?
+            .execute((&name, folder))
+            .map_err(DbError::Update)?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Replaces the tags of a save. Tags that are no longer used by any save are removed.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_tags(db: State<'_, Database>, name: String, tags: Vec<String>) -> DbResult<()> {
+    db.run(move |conn| {
+        let tags: BTreeSet<&str> = tags
+            .iter()
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        tx.prepare_cached("DELETE FROM save_tag WHERE save_id = (SELECT id FROM save WHERE name = ?1 AND deleted IS NULL)")
+            .map_err(DbError::Execution)?
+            .execute([&name])
+            .map_err(DbError::Update)?;
+
+        for tag in tags {
+            tx.prepare_cached("INSERT OR IGNORE INTO tag (name) VALUES (?1)")
+                .map_err(DbError::Execution)?
+                .execute([tag])
+                .map_err(DbError::Update)?;
+
+            tx.prepare_cached(
+                "INSERT OR IGNORE INTO save_tag (
+                    save_id, tag_id
+                ) SELECT s.id, t.id FROM save s, tag t WHERE s.name = ?1 AND s.deleted IS NULL AND t.name = ?2",
+            )
+            .map_err(DbError::Execution)?
+            .execute([name.as_str(), tag])
+            .map_err(DbError::Update)?;
+        }
+
+        tx.prepare_cached("DELETE FROM tag WHERE id NOT IN (SELECT tag_id FROM save_tag)")
+            .map_err(DbError::Execution)?
+            .execute([])
+            .map_err(DbError::Update)?;
+
+        tx.commit().map_err(DbError::Update)
+    })
+    .await
+}
+
+/// Lists every tag used by at least one save, in alphabetical order.
+/// Tags only used by saves in the trash are left out.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn fetch_tags(db: State<'_, Database>) -> DbResult<Box<[Box<str>]>> {
+    db.run(|conn| {
+        let query = conn
+            .prepare_cached(
+                "SELECT t.name FROM tag t WHERE EXISTS (
+                    SELECT 1 FROM save_tag st JOIN save s ON st.save_id = s.id
+                    WHERE st.tag_id = t.id AND s.deleted IS NULL
+                ) ORDER BY t.name",
+            )
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| row.get("name"))
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[Box<str>]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
+
+/// Lists every folder containing at least one save, in alphabetical order.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn fetch_folders(db: State<'_, Database>) -> DbResult<Box<[Box<str>]>> {
+    db.run(|conn| {
+        let query = conn
+            .prepare_cached("SELECT DISTINCT folder FROM save WHERE deleted IS NULL AND folder IS NOT NULL ORDER BY folder")
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| row.get("folder"))
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[Box<str>]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
@@ -0,0 +1,156 @@
+use crate::{
+    base::Save,
+    db::{
+        get_elapsed_time, get_save_from_name, map_fetch_error, write_save, Database, DbError,
+        DbResult,
+    },
+    history::History,
+};
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, Error as SqlError};
+use serde::Serialize;
+use tauri::State;
+
+const MAX_REVISIONS: u32 = 20; // Maximum number of revisions kept per save
+const MAX_REVISION_AGE: i64 = 30; // Maximum age of revisions (in days)
+
+/// Stores the current data of a save as a revision, unless it is the same as `new_data`.
+pub(crate) fn record(conn: &Connection, name: &str, new_data: &Save) -> DbResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO save_revision (
+            save_id, created, data
+        ) SELECT id, last_modified, data FROM save WHERE name = ?1 AND deleted IS NULL AND data IS NOT ?2",
+    )
+    .map_err(DbError::Execution)?
+    .execute((name, new_data))
+    .map_err(DbError::Update)?;
+
+    Ok(())
+}
+
+/// Deletes revisions of a save beyond the revision limit, along with revisions of any save
+/// that are older than the maximum revision age, except for quarantined saves.
+pub(crate) fn prune(conn: &Connection, name: &str) -> DbResult<()> {
+    conn.prepare_cached(
+        "DELETE FROM save_revision
+        WHERE save_id = (SELECT id FROM save WHERE name = ?1 AND deleted IS NULL)
+        AND id NOT IN (
+            SELECT r.id FROM save_revision r JOIN save s ON r.save_id = s.id
+            WHERE s.name = ?1 AND s.deleted IS NULL ORDER BY r.created DESC, r.id DESC LIMIT ?2
+        )",
+    )
+    .map_err(DbError::Execution)?
+    .execute((name, MAX_REVISIONS))
+    .map_err(DbError::Update)?;
+
+    // Revisions of quarantined saves are kept, since they may be the only readable copies
+    conn.prepare_cached(
+        "DELETE FROM save_revision
+        WHERE created < ?1 AND save_id NOT IN (SELECT save_id FROM quarantine)",
+    )
+    .map_err(DbError::Execution)?
+    .execute([Utc::now() - Duration::days(MAX_REVISION_AGE)])
+    .map_err(DbError::Update)?;
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Serialize)]
+pub struct RevisionData {
+    id: i64,
+    created: f32,
+}
+
+fn get_revision_data(conn: &Connection, name: &str, id: i64) -> DbResult<Save> {
+    conn.prepare_cached(
+        "SELECT r.data FROM save_revision r JOIN save s ON r.save_id = s.id
+        WHERE s.name = ?1 AND s.deleted IS NULL AND r.id = ?2",
+    )
+    .map_err(DbError::Execution)?
+    .query_row((name, id), |row| row.get("data"))
+    .map_err(map_fetch_error)
+}
+
+/// Lists the revisions of a save, from newest to oldest.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_revisions(
+    db: State<'_, Database>,
+    name: String,
+) -> DbResult<Box<[RevisionData]>> {
+    db.run(move |conn| {
+        let now = Utc::now();
+
+        let query = conn
+            .prepare_cached(
+                "SELECT r.id, r.created FROM save_revision r JOIN save s ON r.save_id = s.id
+                WHERE s.name = ?1 AND s.deleted IS NULL ORDER BY r.created DESC, r.id DESC",
+            )
+            .map_err(DbError::Execution)?
+            .query_and_then([&name], |row| {
+                Ok(RevisionData {
+                    id: row.get("id")?,
+                    created: get_elapsed_time(row.get("created")?, now),
+                })
+            })
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[RevisionData]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
+
+/// Returns the data of a revision without restoring it.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_revision(db: State<'_, Database>, name: String, id: i64) -> DbResult<Save> {
+    db.run(move |conn| get_revision_data(conn, &name, id)).await
+}
+
+/// Replaces the data of a save with one of its revisions and returns the restored data.
+/// The replaced data is kept as a new revision, and the restore can be undone.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn restore_revision(
+    db: State<'_, Database>,
+    history: State<'_, History>,
+    name: String,
+    id: i64,
+) -> DbResult<Save> {
+    let history = history.inner().clone();
+
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let old = get_save_from_name(&*tx, &name).ok();
+        let save = get_revision_data(&tx, &name, id)?;
+
+        write_save(&tx, &name, &save)?;
+        tx.commit().map_err(DbError::Update)?;
+
+        if let Some(old) = old {
+            history.record(&name, old);
+        }
+
+        Ok(save)
+    })
+    .await
+}
@@ -0,0 +1,391 @@
+use crate::{
+    base::{CharData, Save},
+    db::{get_save_from_name, read_file, write_save, Database, DbError, DbResult},
+    gamedata::{tier_from_progress, GameData, Tier, MAX_TIER},
+    history::History,
+    search::FactKind,
+};
+use ahash::HashMap;
+use rusqlite::{Connection, Error as SqlError};
+use serde::{Deserialize, Serialize};
+use std::{mem, ops::Deref, path::PathBuf};
+use tauri::State;
+
+// An operator in an operator tracker's export (e.g. Krooster)
+#[derive(Deserialize)]
+struct TrackedChar {
+    id: Box<str>,
+    #[serde(alias = "elite", default)]
+    promotion: u8,
+    #[serde(default = "default_level")]
+    level: u8,
+    #[serde(default = "default_owned")]
+    owned: bool,
+}
+
+const fn default_level() -> u8 {
+    1
+}
+
+const fn default_owned() -> bool {
+    true
+}
+
+// Trackers export operators either as a list or keyed by operator ID
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TrackerExport {
+    List(Vec<TrackedChar>),
+    Map(HashMap<Box<str>, TrackedChar>),
+}
+
+impl TrackerExport {
+    fn into_chars(self) -> Vec<TrackedChar> {
+        match self {
+            Self::List(chars) => chars,
+            Self::Map(chars) => chars.into_values().collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RosterImport {
+    imported: usize,              // Number of operators added to or updated in the roster
+    unknown_ids: Box<[Box<str>]>, // Operator IDs missing from the game data, which were left out
+}
+
+// Parses an operator tracker export, returning the known operators and the unknown IDs.
+fn parse_tracker_export(
+    contents: &[u8],
+    data: &GameData,
+) -> DbResult<(Vec<CharData>, Vec<Box<str>>)> {
+    let export: TrackerExport =
+        serde_json::from_slice(contents).map_err(|e| DbError::ImportFormat(Some(e)))?;
+
+    let mut chars = Vec::new();
+    let mut unknown_ids = Vec::new();
+
+    for tracked in export.into_chars() {
+        if !tracked.owned {
+            continue;
+        }
+        if data.operator(&tracked.id).is_none() {
+            unknown_ids.push(tracked.id);
+            continue;
+        }
+
+        chars.push(CharData {
+            char: tracked.id,
+            tier: tier_from_progress(tracked.promotion, tracked.level),
+        });
+    }
+
+    chars.sort_by(|a, b| a.char.cmp(&b.char));
+    unknown_ids.sort();
+
+    Ok((chars, unknown_ids))
+}
+
+/// Returns the operators in the shared roster, in order of ID.
+pub(crate) fn get_shared_roster(conn: &Connection) -> DbResult<Vec<CharData>> {
+    conn.prepare_cached("SELECT char, tier FROM roster ORDER BY char")
+        .map_err(DbError::Execution)?
+        .query_and_then([], |row| {
+            Ok(CharData {
+                char: row.get("char")?,
+                tier: row.get("tier")?,
+            })
+        })
+        .map_err(DbError::Fetching)?
+        .collect::<Result<Vec<CharData>, SqlError>>()
+        .map_err(DbError::Fetching)
+}
+
+/// Replaces the roster of a save with the roster that it uses:
+/// the shared roster, with operators in the save's own roster overriding it.
+pub(crate) fn resolve_roster(save: &mut Save, shared: &[CharData]) {
+    let overrides = mem::take(&mut save.chars).into_vec();
+
+    let mut chars: Vec<CharData> = shared
+        .iter()
+        .filter(|data| !overrides.iter().any(|c| c.char == data.char))
+        .cloned()
+        .collect();
+    chars.extend(overrides);
+    chars.sort_by(|a, b| a.char.cmp(&b.char));
+
+    save.chars = chars.into_boxed_slice();
+}
+
+/// Returns a save along with the roster that it uses.
+/// This should only be used where a save is analyzed or leaves the app (e.g. exported or
+/// shared), since the shared roster does not go with it. Saves copied within the database
+/// keep their own roster, so that they go on using the shared roster.
+pub(crate) fn get_resolved_save<C>(conn: C, name: &str) -> DbResult<Save>
+where
+    C: Deref<Target = Connection>,
+{
+    let mut save = get_save_from_name(&*conn, name)?;
+    resolve_roster(&mut save, &get_shared_roster(&conn)?);
+
+    Ok(save)
+}
+
+fn set_shared_char(conn: &Connection, char: &str, tier: Tier) -> DbResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO roster (
+            char, tier
+        ) VALUES (
+            ?1, ?2
+        ) ON CONFLICT (char) DO UPDATE SET tier = excluded.tier",
+    )
+    .map_err(DbError::Execution)?
+    .execute((char, tier))
+    .map_err(DbError::Update)?;
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct RosterChar {
+    char: Box<str>,
+    tier: Tier,
+    overridden: bool, // Whether the save overrides the shared roster for this operator
+}
+
+/// Lists the operators in the shared roster, or the roster used by a save if `name` is given.
+/// A save uses the shared roster, except for operators in its own roster,
+/// which override the shared roster.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_roster(
+    db: State<'_, Database>,
+    name: Option<String>,
+) -> DbResult<Box<[RosterChar]>> {
+    db.run(move |conn| {
+        let shared = get_shared_roster(conn)?;
+        let overrides = match name {
+            Some(name) => get_save_from_name(&*conn, &name)?.chars.into_vec(),
+            None => Vec::new(),
+        };
+
+        let mut chars: Vec<RosterChar> = shared
+            .into_iter()
+            .filter(|data| !overrides.iter().any(|c| c.char == data.char))
+            .map(|data| RosterChar {
+                char: data.char,
+                tier: data.tier,
+                overridden: false,
+            })
+            .collect();
+        chars.extend(overrides.into_iter().map(|data| RosterChar {
+            char: data.char,
+            tier: data.tier,
+            overridden: true,
+        }));
+        chars.sort_by(|a, b| a.char.cmp(&b.char));
+
+        Ok(chars.into_boxed_slice())
+    })
+    .await
+}
+
+/// Adds an operator to the shared roster, or changes their tier if they are already in it.
+///
+/// # Errors
+/// Returns error if:
+/// - There is no operator with ID `char`
+/// - `tier` is above the highest tier
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_roster_char(
+    db: State<'_, Database>,
+    data: State<'_, GameData>,
+    char: String,
+    tier: Tier,
+) -> DbResult<()> {
+    if data.operator(&char).is_none() {
+        return Err(DbError::UnknownChar);
+    }
+    if tier > MAX_TIER {
+        return Err(DbError::UnknownTier(MAX_TIER));
+    }
+
+    db.run(move |conn| set_shared_char(conn, &char, tier)).await
+}
+
+/// Removes an operator from the shared roster.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn delete_roster_char(db: State<'_, Database>, char: String) -> DbResult<()> {
+    db.run(move |conn| {
+        conn.prepare_cached("DELETE FROM roster WHERE char = ?1")
+            .map_err(DbError::Execution)?
+            .execute([&char])
+            .map_err(DbError::Deletion)?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct AffectedSave {
+    name: Box<str>,
+    assigned: bool, // Whether the operator is assigned to a room in the save
+}
+
+/// Lists the saves affected by a change to an operator in the shared roster, in alphabetical order.
+/// These are the saves that do not override the operator in their own roster.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn find_roster_usage(
+    db: State<'_, Database>,
+    char: String,
+) -> DbResult<Box<[AffectedSave]>> {
+    db.run(move |conn| {
+        let query = conn
+            .prepare_cached(
+                "SELECT s.name, EXISTS (
+                    SELECT 1 FROM save_fact f WHERE f.save_id = s.id AND f.kind = ?2 AND f.value = ?1
+                ) AS assigned FROM save s
+                WHERE s.deleted IS NULL AND NOT EXISTS (
+                    SELECT 1 FROM save_fact f WHERE f.save_id = s.id AND f.kind = ?3 AND f.value = ?1
+                ) ORDER BY s.name",
+            )
+            .map_err(DbError::Execution)?
+            .query_and_then((&char, FactKind::Operator, FactKind::Roster), |row| {
+                Ok(AffectedSave {
+                    name: row.get("name")?,
+                    assigned: row.get("assigned")?,
+                })
+            })
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[AffectedSave]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
+
+/// Updates the shared roster, or the roster of a save if `name` is given,
+/// from an operator tracker export (e.g. Krooster),
+/// given as a list of operators or a map from operator ID to operator.
+/// Each operator needs an `id`, and can have a `promotion` (or `elite`) and a `level`.
+/// Operators marked as not owned are skipped.
+/// Imported operators are added to the roster, or replace the operator's existing entry.
+///
+/// # Errors
+/// Returns error if:
+/// - The file cannot be read
+/// - The file is not an operator tracker export
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_roster(
+    db: State<'_, Database>,
+    data: State<'_, GameData>,
+    history: State<'_, History>,
+    name: Option<String>,
+    path: PathBuf,
+) -> DbResult<RosterImport> {
+    let contents = read_file(path).await?;
+    let (imported, unknown_ids) = parse_tracker_export(&contents, &data)?;
+    let count = imported.len();
+
+    let Some(name) = name else {
+        db.run(move |conn| {
+            let tx = conn.transaction().map_err(DbError::Execution)?;
+            for data in imported {
+                set_shared_char(&tx, &data.char, data.tier)?;
+            }
+            tx.commit().map_err(DbError::Update)
+        })
+        .await?;
+
+        return Ok(RosterImport {
+            imported: count,
+            unknown_ids: unknown_ids.into_boxed_slice(),
+        });
+    };
+
+    let history = history.inner().clone();
+
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let mut save = get_save_from_name(&*tx, &name)?;
+        // The old data is kept for undoing
+        let old = save.clone();
+
+        let mut chars = save.chars.into_vec();
+        for data in imported {
+            match chars.iter_mut().find(|c| c.char == data.char) {
+                Some(existing) => existing.tier = data.tier,
+                None => chars.push(data),
+            }
+        }
+        save.chars = chars.into_boxed_slice();
+
+        write_save(&tx, &name, &save)?;
+        tx.commit().map_err(DbError::Update)?;
+
+        history.record(&name, old);
+
+        Ok(())
+    })
+    .await?;
+
+    Ok(RosterImport {
+        imported: count,
+        unknown_ids: unknown_ids.into_boxed_slice(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        db::{duplicate, insert_save},
+        schema::migrate,
+    };
+
+    #[test]
+    fn duplicates_follow_shared_roster() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        set_shared_char(&conn, "char_102_texas", 1).unwrap();
+        insert_save(&conn, "Base", &Save::default()).unwrap();
+
+        let name = duplicate(&mut conn, "Base").unwrap();
+        set_shared_char(&conn, "char_102_texas", 2).unwrap();
+
+        // The copy does not override the shared roster, so it sees the edit
+        assert!(get_save_from_name(&conn, &name).unwrap().chars.is_empty());
+        let resolved = get_resolved_save(&conn, &name).unwrap();
+        assert_eq!(resolved.chars.len(), 1);
+        assert_eq!(&*resolved.chars[0].char, "char_102_texas");
+        assert_eq!(resolved.chars[0].tier, 2);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    base::{Clock, Save, ShiftCount},
+    db::{get_save_from_name, Database, DbError, DbResult},
+};
+use chrono::DateTime;
+use serde::Serialize;
+use tauri::State;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const DAILY_RESET: i64 = 4 * 3600; // Daily reset happens at 04:00 server time
+const MAX_SCHEDULE_DAYS: u32 = 7; // Longest span that repeated cycles can cover
+
+/// Real-time placement of a save's shifts, starting from the save's rotation start time
+#[derive(Serialize)]
+pub struct Schedule {
+    shifts: Box<[ShiftTime]>,
+    days: Box<[GameDay]>,
+}
+
+#[derive(Serialize)]
+struct ShiftTime {
+    shift: ShiftCount,
+    start: i64, // Unix timestamp (in seconds)
+    end: i64,   // Unix timestamp (in seconds)
+}
+
+// One server day, running from one daily reset to the next
+#[derive(Serialize)]
+struct GameDay {
+    date: Box<str>, // Server-time date on which the day starts, formatted as YYYY-MM-DD
+    shifts: Vec<ShiftMinutes>,
+}
+
+// Portion of a shift that falls within a server day
+#[derive(Serialize)]
+struct ShiftMinutes {
+    shift: ShiftCount,
+    minutes: u32,
+}
+
+// Returns the index of the server day containing a timestamp, counting from the Unix epoch.
+fn game_day(clock: Clock, time: i64) -> i64 {
+    (time + clock.server.utc_offset() - DAILY_RESET).div_euclid(SECONDS_PER_DAY)
+}
+
+// Returns the timestamp of the daily reset that ends a server day.
+fn next_reset(clock: Clock, day: i64) -> i64 {
+    (day + 1) * SECONDS_PER_DAY + DAILY_RESET - clock.server.utc_offset()
+}
+
+fn format_day(day: i64) -> Box<str> {
+    DateTime::from_timestamp(day * SECONDS_PER_DAY, 0)
+        .expect("Failed to convert server day to date")
+        .format("%Y-%m-%d")
+        .to_string()
+        .into_boxed_str()
+}
+
+impl Schedule {
+    fn from_save(save: &Save, clock: Clock, cycles: u16) -> Self {
+        let mut shifts = Vec::new();
+        let mut days: Vec<(i64, Vec<ShiftMinutes>)> = Vec::new();
+
+        let mut time = clock.start;
+
+        for _ in 0..cycles {
+            for shift in 0..save.max_shift {
+                let start = time;
+                let end = start + i64::from(save.shift_duration(shift)) * 60;
+
+                // Split the shift at every daily reset it crosses
+                while time < end {
+                    let day = game_day(clock, time);
+                    let segment_end = end.min(next_reset(clock, day));
+                    let minutes = u32::try_from((segment_end - time) / 60)
+                        .expect("Failed to convert shift duration to u32");
+
+                    match days.last_mut() {
+                        Some((last, entries)) if *last == day => {
+                            match entries.iter_mut().find(|entry| entry.shift == shift) {
+                                Some(entry) => entry.minutes += minutes,
+                                None => entries.push(ShiftMinutes { shift, minutes }),
+                            }
+                        }
+                        _ => days.push((day, vec![ShiftMinutes { shift, minutes }])),
+                    }
+
+                    time = segment_end;
+                }
+
+                shifts.push(ShiftTime { shift, start, end });
+            }
+        }
+
+        Self {
+            shifts: shifts.into_boxed_slice(),
+            days: days
+                .into_iter()
+                .map(|(day, shifts)| GameDay {
+                    date: format_day(day),
+                    shifts,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Places `cycles` repetitions of a save's rotation (1 by default) in real time.
+/// Repeated cycles can cover at most `MAX_SCHEDULE_DAYS` days, though a single cycle is always allowed.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - The save has no rotation start time
+/// - The cycles cover too many days
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_schedule(
+    db: State<'_, Database>,
+    name: String,
+    cycles: Option<u16>,
+) -> DbResult<Schedule> {
+    let save = db
+        .run(move |conn| get_save_from_name(&*conn, &name))
+        .await?;
+    let clock = save.clock.ok_or(DbError::NoStartTime)?;
+
+    let cycles = cycles.unwrap_or(1);
+    let cycle_minutes: u64 = (0..save.max_shift)
+        .map(|shift| u64::from(save.shift_duration(shift)))
+        .sum();
+    if cycles > 1 && u64::from(cycles) * cycle_minutes > u64::from(MAX_SCHEDULE_DAYS) * 24 * 60 {
+        return Err(DbError::ScheduleLength(MAX_SCHEDULE_DAYS));
+    }
+
+    Ok(Schedule::from_save(&save, clock, cycles))
+}
@@ -0,0 +1,423 @@
+use crate::db::SetupError;
+use chrono::Utc;
+use rusqlite::{Connection, DatabaseName, Error as SqlError, Transaction};
+use std::path::Path;
+
+struct Migration {
+    // Whether the migration can lose data (e.g. by dropping or rebuilding tables).
+    // The database file is backed up before any destructive migration is applied.
+    destructive: bool,
+    apply: fn(&Transaction<'_>) -> Result<(), SqlError>,
+}
+
+// Migration `i` upgrades the schema from version `i` to version `i + 1`.
+// Never edit or reorder released migrations; append new ones instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        destructive: false,
+        apply: create_save_table,
+    },
+    Migration {
+        destructive: true,
+        apply: add_save_revisions,
+    },
+    Migration {
+        destructive: false,
+        apply: add_templates,
+    },
+    Migration {
+        destructive: false,
+        apply: add_save_metadata,
+    },
+    Migration {
+        destructive: false,
+        apply: add_save_facts,
+    },
+    Migration {
+        destructive: true,
+        apply: add_trash,
+    },
+    Migration {
+        destructive: false,
+        apply: add_quarantine,
+    },
+    Migration {
+        destructive: false,
+        apply: add_roster,
+    },
+];
+
+/// Schema version of a fully-migrated database
+pub(crate) const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+// Databases created before schema versioning already have this table,
+// so this migration has to tolerate it existing.
+fn create_save_table(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS save (
+            name            TEXT NOT NULL UNIQUE,
+            created         TEXT NOT NULL,
+            last_modified   TEXT NOT NULL,
+            data            BLOB NOT NULL
+        ) STRICT;
+        CREATE INDEX IF NOT EXISTS idx_name_data ON save (
+            name, data
+        );",
+    )
+}
+
+// Saves get an explicit ID so that revisions can reference them across renames.
+// SQLite cannot add a primary key to an existing table, so the save table is rebuilt.
+fn add_save_revisions(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE save_new (
+            id              INTEGER PRIMARY KEY,
+            name            TEXT NOT NULL UNIQUE,
+            created         TEXT NOT NULL,
+            last_modified   TEXT NOT NULL,
+            data            BLOB NOT NULL
+        ) STRICT;
+        INSERT INTO save_new (
+            id, name, created, last_modified, data
+        ) SELECT rowid, name, created, last_modified, data FROM save;
+        DROP TABLE save;
+        ALTER TABLE save_new RENAME TO save;
+        CREATE INDEX idx_name_data ON save (
+            name, data
+        );
+        CREATE TABLE save_revision (
+            id              INTEGER PRIMARY KEY,
+            save_id         INTEGER NOT NULL REFERENCES save (id) ON DELETE CASCADE,
+            created         TEXT NOT NULL,
+            data            BLOB NOT NULL
+        ) STRICT;
+        CREATE INDEX idx_save_revision ON save_revision (
+            save_id, created
+        );",
+    )
+}
+
+fn add_templates(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE template (
+            id              INTEGER PRIMARY KEY,
+            name            TEXT NOT NULL UNIQUE,
+            created         TEXT NOT NULL,
+            data            BLOB NOT NULL
+        ) STRICT;",
+    )
+}
+
+fn add_save_metadata(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "ALTER TABLE save ADD COLUMN description TEXT NOT NULL DEFAULT '';
+        ALTER TABLE save ADD COLUMN folder TEXT;
+        CREATE INDEX idx_save_folder ON save (
+            folder
+        );
+        CREATE TABLE tag (
+            id              INTEGER PRIMARY KEY,
+            name            TEXT NOT NULL UNIQUE
+        ) STRICT;
+        CREATE TABLE save_tag (
+            save_id         INTEGER NOT NULL REFERENCES save (id) ON DELETE CASCADE,
+            tag_id          INTEGER NOT NULL REFERENCES tag (id) ON DELETE CASCADE,
+            PRIMARY KEY (save_id, tag_id)
+        ) STRICT, WITHOUT ROWID;
+        CREATE INDEX idx_save_tag ON save_tag (
+            tag_id
+        );",
+    )
+}
+
+// Saves are indexed by `search::reindex` once migrations finish, so that this migration
+// does not depend on how facts are currently extracted.
+fn add_save_facts(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE save_fact (
+            save_id         INTEGER NOT NULL REFERENCES save (id) ON DELETE CASCADE,
+            kind            TEXT NOT NULL,
+            value           TEXT NOT NULL,
+            PRIMARY KEY (save_id, kind, value)
+        ) STRICT, WITHOUT ROWID;
+        CREATE INDEX idx_save_fact ON save_fact (
+            kind, value
+        );",
+    )
+}
+
+// Trashed saves keep their names, so names only have to be unique among other saves.
+// SQLite cannot drop a constraint from an existing table, so the save table is rebuilt.
+fn add_trash(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE save_new (
+            id              INTEGER PRIMARY KEY,
+            name            TEXT NOT NULL,
+            created         TEXT NOT NULL,
+            last_modified   TEXT NOT NULL,
+            data            BLOB NOT NULL,
+            description     TEXT NOT NULL DEFAULT '',
+            folder          TEXT,
+            deleted         TEXT
+        ) STRICT;
+        INSERT INTO save_new (
+            id, name, created, last_modified, data, description, folder
+        ) SELECT id, name, created, last_modified, data, description, folder FROM save;
+        DROP TABLE save;
+        ALTER TABLE save_new RENAME TO save;
+        CREATE UNIQUE INDEX idx_save_name ON save (
+            name
+        ) WHERE deleted IS NULL;
+        CREATE INDEX idx_name_data ON save (
+            name, data
+        );
+        CREATE INDEX idx_save_folder ON save (
+            folder
+        );
+        CREATE INDEX idx_save_deleted ON save (
+            deleted
+        ) WHERE deleted IS NOT NULL;
+        CREATE TABLE setting (
+            key             TEXT PRIMARY KEY,
+            value           ANY
+        ) STRICT;",
+    )
+}
+
+// Quarantined saves stay in the save table, hidden like trashed saves, so that they keep
+// their metadata, tags and revisions.
+fn add_quarantine(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE quarantine (
+            id              INTEGER PRIMARY KEY,
+            save_id         INTEGER NOT NULL UNIQUE REFERENCES save (id) ON DELETE CASCADE,
+            quarantined     TEXT NOT NULL,
+            reason          TEXT NOT NULL
+        ) STRICT;",
+    )
+}
+
+// Operators in the shared roster, which saves use unless they override them
+fn add_roster(tx: &Transaction<'_>) -> Result<(), SqlError> {
+    tx.execute_batch(
+        "CREATE TABLE roster (
+            char            TEXT PRIMARY KEY,
+            tier            INTEGER NOT NULL
+        ) STRICT;",
+    )
+}
+
+pub(crate) fn get_schema_version(conn: &Connection) -> Result<usize, SqlError> {
+    let version: i64 =
+        conn.pragma_query_value(Some(DatabaseName::Main), "user_version", |row| row.get(0))?;
+
+    // A negative version can only be set by something other than this app
+    Ok(usize::try_from(version).unwrap_or(usize::MAX))
+}
+
+/// Returns whether the database has a save table, as every database used by the app does.
+pub(crate) fn has_save_table(conn: &Connection) -> Result<bool, SqlError> {
+    conn.prepare("SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = 'save'")?
+        .exists([])
+}
+
+// Returns whether the database holds data that a migration could lose.
+// Fresh databases have nothing to back up, but databases created before schema versioning
+// are also at version 0, and are told apart by their save table.
+fn has_data(conn: &Connection, version: usize) -> Result<bool, SqlError> {
+    Ok(version > 0 || has_save_table(conn)?)
+}
+
+/// Writes a copy of the database next to the original file, tagged with `label`
+/// (e.g. the schema version it was taken at) and the time.
+///
+/// # Errors
+/// Returns error if the copy cannot be written.
+pub(crate) fn backup(conn: &Connection, label: &str) -> Result<(), SetupError> {
+    let Some(db_path) = conn.path().filter(|path| !path.is_empty()) else {
+        // In-memory databases have nothing to back up
+        return Ok(());
+    };
+
+    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+    let backup_path = Path::new(db_path).with_extension(format!("{label}-{timestamp}.bak"));
+
+    conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy()])
+        .map_err(SetupError::Backup)?;
+
+    Ok(())
+}
+
+/// Applies every migration that has not yet been applied to the database.
+/// Each migration runs in its own transaction, so a failed migration leaves the
+/// database at the last successfully-applied version.
+///
+/// # Errors
+/// Returns error if:
+/// - The database has a newer schema version than this app supports
+/// - The database has data, and cannot be backed up before a destructive migration
+/// - A migration fails or leaves dangling references
+pub(crate) fn migrate(conn: &mut Connection) -> Result<(), SetupError> {
+    let version = get_schema_version(conn)?;
+
+    if version > SCHEMA_VERSION {
+        return Err(SetupError::NewerSchema {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    let pending = &MIGRATIONS[version..];
+
+    if pending.iter().any(|migration| migration.destructive) && has_data(conn, version)? {
+        backup(conn, &format!("v{version}"))?;
+    }
+
+    for (i, migration) in pending.iter().enumerate() {
+        let tx = conn.transaction()?;
+        (migration.apply)(&tx)?;
+
+        // Foreign keys are not enforced during migrations, so check them before committing
+        if tx.prepare("PRAGMA foreign_key_check")?.exists([])? {
+            return Err(SetupError::ForeignKeyViolation(version + i + 1));
+        }
+
+        tx.pragma_update(Some(DatabaseName::Main), "user_version", version + i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, path::PathBuf};
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.prepare("SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = ?1")
+            .and_then(|mut stmt| stmt.exists([name]))
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        for table in [
+            "save",
+            "save_revision",
+            "template",
+            "tag",
+            "save_tag",
+            "save_fact",
+            "setting",
+            "quarantine",
+            "roster",
+        ] {
+            assert!(table_exists(&conn, table), "missing table {table}");
+        }
+
+        // Migrating an up-to-date database does nothing
+        migrate(&mut conn).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn upgrades_unversioned_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // Schema of databases created before schema versioning
+        conn.execute_batch(
+            "CREATE TABLE save (
+                name            TEXT NOT NULL UNIQUE,
+                created         TEXT NOT NULL,
+                last_modified   TEXT NOT NULL,
+                data            BLOB NOT NULL
+            ) STRICT;
+            CREATE INDEX idx_name_data ON save (
+                name, data
+            );
+            INSERT INTO save (
+                name, created, last_modified, data
+            ) VALUES (
+                'Old save', '2024-01-01T00:00:00Z', '2024-01-02T00:00:00Z', x'00'
+            );",
+        )
+        .unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(get_schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let (id, name, description, deleted): (i64, String, String, Option<String>) = conn
+            .query_row(
+                "SELECT id, name, description, deleted FROM save",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(name, "Old save");
+        assert_eq!(description, "");
+        assert_eq!(deleted, None);
+    }
+
+    // Returns an empty directory for a test's database files.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("riic-schema-{}-{name}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn count_backups(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| {
+                let path = entry.as_ref().unwrap().path();
+                path.extension().map_or(false, |ext| ext == "bak")
+            })
+            .count()
+    }
+
+    #[test]
+    fn backs_up_only_databases_with_data() {
+        let dir = test_dir("backup");
+
+        let mut fresh = Connection::open(dir.join("fresh.db")).unwrap();
+        migrate(&mut fresh).unwrap();
+        assert_eq!(count_backups(&dir), 0);
+
+        let mut unversioned = Connection::open(dir.join("unversioned.db")).unwrap();
+        unversioned
+            .execute_batch(
+                "CREATE TABLE save (
+                    name            TEXT NOT NULL UNIQUE,
+                    created         TEXT NOT NULL,
+                    last_modified   TEXT NOT NULL,
+                    data            BLOB NOT NULL
+                ) STRICT;",
+            )
+            .unwrap();
+        migrate(&mut unversioned).unwrap();
+        assert_eq!(count_backups(&dir), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        assert!(matches!(
+            migrate(&mut conn),
+            Err(SetupError::NewerSchema { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{
+    base::Save,
+    db::{Database, DbError, DbResult},
+};
+use rusqlite::{
+    types::{ToSql, ToSqlOutput},
+    Connection, Error as SqlError, OptionalExtension,
+};
+use serde::Deserialize;
+use std::{borrow::Cow, collections::BTreeSet};
+use tauri::State;
+
+// Version of the facts extracted by `facts`. Raise it whenever `facts` changes,
+// so that every save is reindexed the next time the app starts.
+const FACT_VERSION: i64 = 1;
+const FACT_VERSION_KEY: &str = "fact_version";
+
+/// Kinds of facts about a save that are indexed for searching
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FactKind {
+    Operator, // An operator assigned to a shift in a built room
+    Roster,   // An operator in the save's roster
+    Facility, // A built facility and its level
+    Product,  // A product made in a built trading post or factory
+}
+
+impl ToSql for FactKind {
+    fn to_sql(&self) -> Result<ToSqlOutput<'_>, SqlError> {
+        let kind = match self {
+            Self::Operator => "operator",
+            Self::Roster => "roster",
+            Self::Facility => "facility",
+            Self::Product => "product",
+        };
+
+        Ok(ToSqlOutput::from(kind))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fact {
+    kind: FactKind,
+    value: Box<str>,
+}
+
+// Returns the facts about a save that can be searched for, as pairs of kind and value.
+// Facility facts are formatted as `facility:level` (e.g. `trading:3`).
+fn facts(save: &Save) -> BTreeSet<(FactKind, Cow<'_, str>)> {
+    let mut facts = BTreeSet::new();
+
+    for room in save.layout.staffed_rooms() {
+        let facility = format!("{}:{}", room.kind().name(), room.level);
+        facts.insert((FactKind::Facility, Cow::Owned(facility)));
+
+        facts.extend(
+            room.chars()
+                .map(|char| (FactKind::Operator, Cow::Borrowed(char))),
+        );
+        facts.extend(
+            (room.products().into_iter())
+                .map(|product| (FactKind::Product, Cow::Borrowed(product))),
+        );
+    }
+
+    for (kind, level) in save.layout.unstaffed_rooms() {
+        let facility = format!("{}:{level}", kind.name());
+        facts.insert((FactKind::Facility, Cow::Owned(facility)));
+    }
+
+    facts.extend((save.chars.iter()).map(|data| (FactKind::Roster, Cow::Borrowed(&*data.char))));
+
+    facts
+}
+
+/// Replaces the indexed facts of a save with the facts about `save`.
+/// This should be called within a transaction.
+pub(crate) fn index(conn: &Connection, id: i64, save: &Save) -> Result<(), SqlError> {
+    conn.prepare_cached("DELETE FROM save_fact WHERE save_id = ?1")?
+        .execute([id])?;
+
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO save_fact (
+            save_id, kind, value
+        ) VALUES (
+            ?1, ?2, ?3
+        )",
+    )?;
+
+    for (kind, value) in facts(save) {
+        insert.execute((id, kind, value))?;
+    }
+
+    Ok(())
+}
+
+/// Reindexes every save, including trashed ones, if the index was built by another version
+/// of `facts`. This runs after migrations, which do not index saves themselves.
+/// Saves that cannot be decoded are left unindexed until they are next written.
+pub(crate) fn reindex(conn: &mut Connection) -> Result<(), SqlError> {
+    let version: Option<i64> = conn
+        .prepare_cached("SELECT value FROM setting WHERE key = ?1")?
+        .query_row([FACT_VERSION_KEY], |row| row.get("value"))
+        .optional()?;
+
+    if version == Some(FACT_VERSION) {
+        return Ok(());
+    }
+
+    let tx = conn.transaction()?;
+
+    let saves = tx
+        .prepare("SELECT id, data FROM save")?
+        .query_and_then([], |row| {
+            let data: Vec<u8> = row.get("data")?;
+            Ok((row.get("id")?, Save::from_blob(&data).ok()))
+        })?
+        .collect::<Result<Vec<_>, SqlError>>()?;
+
+    for (id, save) in saves {
+        if let Some(save) = save {
+            index(&tx, id, &save)?;
+        }
+    }
+
+    tx.prepare_cached(
+        "INSERT INTO setting (
+            key, value
+        ) VALUES (
+            ?1, ?2
+        ) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+    )?
+    .execute((FACT_VERSION_KEY, FACT_VERSION))?;
+
+    tx.commit()
+}
+
+/// Lists the names of saves matching every one of the given facts, in alphabetical order.
+/// Operator facts match operator IDs, and facility facts match `facility:level` pairs
+/// (e.g. `trading:3`).
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn search_saves(db: State<'_, Database>, facts: Vec<Fact>) -> DbResult<Box<[Box<str>]>> {
+    db.run(move |conn| {
+        let mut matches: Option<BTreeSet<Box<str>>> = None;
+
+        for fact in facts {
+            let names = conn
+                .prepare_cached(
+                    "SELECT s.name FROM save_fact f JOIN save s ON f.save_id = s.id
+                    WHERE f.kind = ?1 AND f.value = ?2 AND s.deleted IS NULL",
+                )
+                .map_err(DbError::Execution)?
+                .query_and_then((fact.kind, fact.value), |row| row.get("name"))
+                .map_err(DbError::Fetching)?
+                .collect::<Result<BTreeSet<Box<str>>, SqlError>>()
+                .map_err(DbError::Fetching)?;
+
+            matches = Some(match matches {
+                Some(prev) => prev.intersection(&names).cloned().collect(),
+                None => names,
+            });
+        }
+
+        let names = match matches {
+            Some(names) => names,
+            // Without any facts to match, every save matches
+            None => conn
+                .prepare_cached("SELECT name FROM save WHERE deleted IS NULL")
+                .map_err(DbError::Execution)?
+                .query_and_then([], |row| row.get("name"))
+                .map_err(DbError::Fetching)?
+                .collect::<Result<BTreeSet<Box<str>>, SqlError>>()
+                .map_err(DbError::Fetching)?,
+        };
+
+        Ok(names.into_iter().collect())
+    })
+    .await
+}
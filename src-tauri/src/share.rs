@@ -0,0 +1,203 @@
+use crate::{
+    base::{Save, SaveError},
+    db::{insert_save, Database, DbError, DbResult},
+    roster::get_resolved_save,
+    MAX_SAVE_SIZE,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, DecodeError as Base64Error, Engine as _};
+use bincode::{
+    config::standard, decode_from_slice, encode_to_vec, error::DecodeError, error::EncodeError,
+};
+use miniz_oxide::{deflate::compress_to_vec, inflate::decompress_to_vec_with_limit};
+use tauri::State;
+use thiserror::Error;
+
+// Share codes look like `riic1.<data>`, where the number is the share code format version
+// and the data is the URL-safe base64 encoding of:
+// - A CRC-32 checksum of the compressed payload (4 bytes, little-endian)
+// - The compressed payload, which holds the save name and the save data blob
+const SHARE_PREFIX: &str = "riic";
+const SHARE_VERSION: u32 = 1;
+const CHECKSUM_LEN: usize = 4;
+const COMPRESSION_LEVEL: u8 = 9;
+
+#[derive(Debug, Error)]
+pub enum ShareError {
+    #[error("It is not a share code")]
+    Prefix,
+
+    #[error("It has format version {0}, which this app does not support")]
+    UnsupportedVersion(u32),
+
+    #[error("It contains characters that are not allowed in share codes")]
+    Encoding(#[source] Base64Error),
+
+    #[error("It is incomplete or has been altered")]
+    Checksum,
+
+    #[error("Its contents could not be decompressed")]
+    Compression,
+
+    #[error("Its contents are malformed")]
+    Contents(#[source] DecodeError),
+
+    #[error("{0}")]
+    Save(SaveError),
+}
+
+fn encode(name: &str, save: &Save) -> Result<String, EncodeError> {
+    let payload = encode_to_vec((name, save.to_blob()?), standard())?;
+    let compressed = compress_to_vec(&payload, COMPRESSION_LEVEL);
+
+    let mut data = Vec::with_capacity(CHECKSUM_LEN + compressed.len());
+    data.extend_from_slice(&crc32fast::hash(&compressed).to_le_bytes());
+    data.extend_from_slice(&compressed);
+
+    Ok(format!(
+        "{SHARE_PREFIX}{SHARE_VERSION}.{}",
+        URL_SAFE_NO_PAD.encode(data)
+    ))
+}
+
+fn decode(code: &str) -> Result<(String, Save), ShareError> {
+    let (version, data) = code
+        .trim()
+        .strip_prefix(SHARE_PREFIX)
+        .and_then(|code| code.split_once('.'))
+        .ok_or(ShareError::Prefix)?;
+    let version: u32 = version.parse().map_err(|_| ShareError::Prefix)?;
+    if version != SHARE_VERSION {
+        return Err(ShareError::UnsupportedVersion(version));
+    }
+
+    let data = URL_SAFE_NO_PAD.decode(data).map_err(ShareError::Encoding)?;
+    if data.len() < CHECKSUM_LEN {
+        return Err(ShareError::Checksum);
+    }
+    let (checksum, compressed) = data.split_at(CHECKSUM_LEN);
+    if checksum != crc32fast::hash(compressed).to_le_bytes() {
+        return Err(ShareError::Checksum);
+    }
+
+    let payload = decompress_to_vec_with_limit(compressed, MAX_SAVE_SIZE)
+        .map_err(|_| ShareError::Compression)?;
+    let ((name, blob), _): ((String, Vec<u8>), usize) =
+        decode_from_slice(&payload, standard().with_limit::<MAX_SAVE_SIZE>())
+            .map_err(ShareError::Contents)?;
+    let save = Save::from_blob(&blob).map_err(ShareError::Save)?;
+
+    Ok((name, save))
+}
+
+/// Encodes a save into a compact share code, which can be pasted as text.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - The save cannot be encoded
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn create_share_code(db: State<'_, Database>, name: String) -> DbResult<Box<str>> {
+    db.run(move |conn| {
+        let save = get_resolved_save(&*conn, &name)?;
+        let code = encode(&name, &save).map_err(DbError::ShareEncode)?;
+
+        Ok(code.into_boxed_str())
+    })
+    .await
+}
+
+/// Creates a save from a share code made by `create_share_code`.
+/// If another save has the same name, the new save is given a numbered name.
+/// Returns the name of the new save.
+///
+/// # Errors
+/// Returns error if:
+/// - The share code is malformed, incomplete or of an unsupported version
+/// - The save is invalid
+/// - Invalid SQL statement is present
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn import_share_code(db: State<'_, Database>, code: String) -> DbResult<Box<str>> {
+    let (name, save) = decode(&code).map_err(DbError::ShareCode)?;
+    save.validate().map_err(DbError::Invalid)?;
+
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let name = match name.trim() {
+            "" => "Untitled",
+            name => name,
+        };
+        let name = insert_save(&tx, name, &save)?;
+        tx.commit().map_err(DbError::Creation)?;
+
+        Ok(name)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_code() -> String {
+        encode("Shared save", &Save::default()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_share_code() {
+        let code = sample_code();
+        assert!(code.starts_with("riic1."));
+
+        // Surrounding whitespace from copying and pasting is ignored
+        let (name, save) = decode(&format!("  {code}\n")).unwrap();
+
+        assert_eq!(name, "Shared save");
+        assert!(save == Save::default());
+    }
+
+    #[test]
+    fn rejects_altered_codes() {
+        let code = sample_code();
+        let middle = code.len() / 2;
+        let replacement = if &code[middle..=middle] == "A" {
+            "B"
+        } else {
+            "A"
+        };
+        let altered = format!("{}{replacement}{}", &code[..middle], &code[middle + 1..]);
+
+        assert!(matches!(decode(&altered), Err(ShareError::Checksum)));
+    }
+
+    #[test]
+    fn rejects_incomplete_codes() {
+        let code = sample_code();
+
+        assert!(matches!(
+            decode(&code[..code.len() - 8]),
+            Err(ShareError::Checksum | ShareError::Encoding(_))
+        ));
+        assert!(matches!(decode("riic1."), Err(ShareError::Checksum)));
+    }
+
+    #[test]
+    fn rejects_other_text() {
+        assert!(matches!(decode("hello"), Err(ShareError::Prefix)));
+        assert!(matches!(decode("riicX.abc"), Err(ShareError::Prefix)));
+        assert!(matches!(decode("riic1.a*b"), Err(ShareError::Encoding(_))));
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let code = sample_code().replacen("riic1.", "riic2.", 1);
+
+        assert!(matches!(
+            decode(&code),
+            Err(ShareError::UnsupportedVersion(2))
+        ));
+    }
+}
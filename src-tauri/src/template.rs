@@ -0,0 +1,161 @@
+use crate::{
+    base::{Layout, Save},
+    db::{get_save_from_name, insert_save, map_fetch_error, Database, DbError, DbResult},
+};
+use chrono::Utc;
+use rusqlite::{Connection, Error as SqlError, ErrorCode};
+use serde::Serialize;
+use tauri::State;
+
+// Fully-upgraded layouts, named by their numbers of trading posts, factories and power plants
+const BUILTIN_TEMPLATES: [(&str, usize, usize, usize); 3] =
+    [("2-4-3", 2, 4, 3), ("2-5-2", 2, 5, 2), ("1-5-3", 1, 5, 3)];
+
+fn get_builtin_template(name: &str) -> Option<Save> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(template, ..)| *template == name)
+        .map(|&(_, tp, fac, pp)| Save::with_layout(Layout::upgraded(tp, fac, pp)))
+}
+
+fn get_template(conn: &Connection, name: &str) -> DbResult<Save> {
+    if let Some(save) = get_builtin_template(name) {
+        return Ok(save);
+    }
+
+    conn.prepare_cached("SELECT data FROM template WHERE name = ?1")
+        .map_err(DbError::Execution)?
+        .query_row([name], |row| row.get("data"))
+        .map_err(map_fetch_error)
+}
+
+#[derive(Serialize)]
+pub struct TemplateData {
+    name: Box<str>,
+    builtin: bool,
+}
+
+/// Lists built-in templates, followed by user-saved templates.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn fetch_templates(db: State<'_, Database>) -> DbResult<Box<[TemplateData]>> {
+    db.run(|conn| {
+        let mut templates: Vec<TemplateData> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(name, ..)| TemplateData {
+                name: (*name).into(),
+                builtin: true,
+            })
+            .collect();
+
+        let query = conn
+            .prepare_cached("SELECT name FROM template ORDER BY created")
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| {
+                Ok(TemplateData {
+                    name: row.get("name")?,
+                    builtin: false,
+                })
+            })
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Vec<TemplateData>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        templates.extend(query);
+
+        Ok(templates.into_boxed_slice())
+    })
+    .await
+}
+
+/// Creates a new save from a template.
+/// Returns the name of the new save.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database insertion failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn create_save_from_template(
+    db: State<'_, Database>,
+    template: String,
+) -> DbResult<Box<str>> {
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+        let save = get_template(&tx, &template)?;
+
+        let name = insert_save(&tx, "Untitled", &save)?;
+        tx.commit().map_err(DbError::Creation)?;
+
+        Ok(name)
+    })
+    .await
+}
+
+/// Stores a copy of a save as a user template.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database insertion failed
+/// - A template with name `template` already exists
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn create_template(
+    db: State<'_, Database>,
+    name: String,
+    template: String,
+) -> DbResult<()> {
+    if get_builtin_template(&template).is_some() {
+        return Err(DbError::DuplicateTemplate);
+    }
+
+    db.run(move |conn| {
+        let save = get_save_from_name(&*conn, &name)?;
+
+        conn.prepare_cached(
+            "INSERT INTO template (
+                name, created, data
+            ) VALUES (
+                ?1, ?2, ?3
+            )",
+        )
+        .map_err(DbError::Execution)?
+        .execute((&template, Utc::now(), save))
+        .map_err(|e| match e.sqlite_error_code() {
+            Some(ErrorCode::ConstraintViolation) => DbError::DuplicateTemplate,
+            _ => DbError::Creation(e),
+        })?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Deletes a user template. Built-in templates cannot be deleted.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn delete_template(db: State<'_, Database>, template: String) -> DbResult<()> {
+    db.run(move |conn| {
+        conn.prepare_cached("DELETE FROM template WHERE name = ?1")
+            .map_err(DbError::Execution)?
+            .execute([&template])
+            .map_err(DbError::Deletion)?;
+
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,176 @@
+use crate::db::{get_elapsed_time, get_free_name, Database, DbError, DbResult};
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, Error as SqlError, OptionalExtension};
+use serde::Serialize;
+use tauri::State;
+
+const DEFAULT_TRASH_RETENTION: u32 = 30; // Days that trashed saves are kept, unless configured
+const TRASH_RETENTION_KEY: &str = "trash_retention";
+
+// Returns the number of days that trashed saves are kept, or `None` if they are kept forever.
+fn get_retention(conn: &Connection) -> Result<Option<u32>, SqlError> {
+    let setting: Option<Option<u32>> = conn
+        .prepare_cached("SELECT value FROM setting WHERE key = ?1")?
+        .query_row([TRASH_RETENTION_KEY], |row| row.get("value"))
+        .optional()?;
+
+    Ok(setting.unwrap_or(Some(DEFAULT_TRASH_RETENTION)))
+}
+
+/// Permanently deletes saves that have been in the trash for longer than the retention period.
+pub(crate) fn purge(conn: &Connection) -> Result<(), SqlError> {
+    if let Some(days) = get_retention(conn)? {
+        conn.prepare_cached(
+            "DELETE FROM save WHERE deleted < ?1 AND id NOT IN (SELECT save_id FROM quarantine)",
+        )?
+        .execute([Utc::now() - Duration::days(days.into())])?;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct TrashedSave {
+    id: i64,
+    name: Box<str>,
+    deleted: f32,
+}
+
+/// Lists saves in the trash, from most to least recently deleted.
+/// Trashed saves are identified by ID, since several of them can share a name.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn list_trash(db: State<'_, Database>) -> DbResult<Box<[TrashedSave]>> {
+    db.run(|conn| {
+        let now = Utc::now();
+
+        let query = conn
+            .prepare_cached(
+                "SELECT id, name, deleted FROM save
+                WHERE deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)
+                ORDER BY deleted DESC",
+            )
+            .map_err(DbError::Execution)?
+            .query_and_then([], |row| {
+                Ok(TrashedSave {
+                    id: row.get("id")?,
+                    name: row.get("name")?,
+                    deleted: get_elapsed_time(row.get("deleted")?, now),
+                })
+            })
+            .map_err(DbError::Fetching)?
+            .collect::<Result<Box<[TrashedSave]>, SqlError>>()
+            .map_err(DbError::Fetching)?;
+
+        Ok(query)
+    })
+    .await
+}
+
+/// Moves a save out of the trash.
+/// If another save has taken its name in the meantime, the save is given a numbered name.
+/// Returns the name of the restored save.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn restore_save(db: State<'_, Database>, id: i64) -> DbResult<Box<str>> {
+    db.run(move |conn| {
+        let tx = conn.transaction().map_err(DbError::Execution)?;
+
+        let name: Box<str> = tx
+            .prepare_cached(
+                "SELECT name FROM save
+                WHERE id = ?1 AND deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)",
+            )
+            .map_err(DbError::Execution)?
+            .query_row([id], |row| row.get("name"))
+            .map_err(DbError::Fetching)?;
+
+        let name = get_free_name(&tx, &name)?;
+
+        tx.prepare_cached("UPDATE save SET name = ?2, deleted = NULL WHERE id = ?1")
+            .map_err(DbError::Execution)?
+            .execute((id, &name))
+            .map_err(DbError::Restoration)?;
+
+        tx.commit().map_err(DbError::Restoration)?;
+
+        Ok(name)
+    })
+    .await
+}
+
+/// Permanently deletes every save in the trash.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn empty_trash(db: State<'_, Database>) -> DbResult<()> {
+    db.run(|conn| {
+        conn.prepare_cached(
+            "DELETE FROM save
+            WHERE deleted IS NOT NULL AND id NOT IN (SELECT save_id FROM quarantine)",
+        )
+        .map_err(DbError::Execution)?
+        .execute([])
+        .map_err(DbError::Deletion)?;
+
+        Ok(())
+    })
+    .await
+}
+
+/// Returns the number of days that trashed saves are kept for,
+/// or `None` if they are never purged.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database query failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn get_trash_retention(db: State<'_, Database>) -> DbResult<Option<u32>> {
+    db.run(|conn| get_retention(conn).map_err(DbError::Fetching))
+        .await
+}
+
+/// Sets the number of days that trashed saves are kept for.
+/// If `days` is `None`, trashed saves are never purged.
+/// Expired saves are purged the next time the app starts.
+///
+/// # Errors
+/// Returns error if:
+/// - Invalid SQL statement is present
+/// - Database update failed
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn set_trash_retention(db: State<'_, Database>, days: Option<u32>) -> DbResult<()> {
+    db.run(move |conn| {
+        conn.prepare_cached(
+            "INSERT INTO setting (
+                key, value
+            ) VALUES (
+                ?1, ?2
+            ) ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        )
+        .map_err(DbError::Execution)?
+        .execute((TRASH_RETENTION_KEY, days))
+        .map_err(DbError::Setting)?;
+
+        Ok(())
+    })
+    .await
+}
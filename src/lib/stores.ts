@@ -11,7 +11,12 @@ import listIncreasingIcon from '$lib/images/ui/list-increasing.svg';
 import listDecreasingIcon from '$lib/images/ui/list-decreasing.svg';
 import maximizeIcon from '$lib/images/ui/maximize.svg';
 import minimizeIcon from '$lib/images/ui/minimize.svg';
-import type { SaveData, SaveTimeData } from '$lib/types';
+import {
+	isCommandError,
+	type HealthReport,
+	type SaveData,
+	type SaveTimeData
+} from '$lib/types';
 
 // App-wide error store to display errors to users
 function createError() {
@@ -22,6 +27,8 @@ function createError() {
 			set(err.message);
 		} else if (typeof err === 'string') {
 			set(err);
+		} else if (isCommandError(err)) {
+			set(err.message);
 		}
 	}
 
@@ -43,6 +50,29 @@ function createExportNotice() {
 	};
 }
 
+// Report of the database health check, shown on the main menu until dismissed.
+// The report is only loaded once, so a dismissed report stays hidden.
+// Switching or restoring databases returns a new report, which replaces it with `set`.
+function createHealthReport() {
+	const { subscribe, set } = writable<HealthReport | null>(null);
+
+	let loaded = false;
+
+	return {
+		subscribe,
+		load: () => {
+			if (loaded) return;
+			loaded = true;
+			invoke<HealthReport>('get_health_report').then(set).catch(error.handle);
+		},
+		set: (report: HealthReport) => {
+			loaded = true;
+			set(report);
+		},
+		dismiss: () => set(null)
+	};
+}
+
 // Controls whether the save list on the main menu is sorted by time of creation or last edit
 function createSaveSortMode() {
 	type SortMode = 'modified' | 'created';
@@ -254,6 +284,7 @@ function createZoomScales() {
 
 export const error = createError();
 export const exportNotice = createExportNotice();
+export const healthReport = createHealthReport();
 export const saveSortMode = createSaveSortMode();
 export const saveSortOrder = createSaveSortOrder();
 export const saveList = createSaveList();
@@ -2,6 +2,9 @@ export type SaveTimeData = {
 	name: string;
 	readonly modified: number;
 	readonly created: number;
+	description: string;
+	folder: string | null;
+	tags: string[];
 };
 
 export type SaveData = {
@@ -23,6 +26,11 @@ export type SaveData = {
 	drones: number;
 	maxShift: number;
 	interval: number;
+	durations: number[];
+	clock?: {
+		start: number;
+		server: 'us' | 'cn';
+	};
 };
 
 type NoShiftFacility = {
@@ -60,3 +68,34 @@ export type FacilityName =
 	| 'trading'
 	| 'training'
 	| 'workshop';
+
+// Result of the database health check, which runs when the app starts
+export type HealthReport = {
+	error: string | null;
+	problems: string[];
+	repaired: boolean;
+	quarantined: {
+		id: number;
+		name: string;
+		reason: string;
+		quarantined: number;
+		revisions: number;
+	}[];
+	newerSaves: string[];
+};
+
+// Error returned by a failed command
+export type CommandError = {
+	code: string;
+	message: string;
+	details: string | null;
+};
+
+export function isCommandError(err: unknown): err is CommandError {
+	return (
+		typeof err === 'object' &&
+		err !== null &&
+		typeof (err as CommandError).code === 'string' &&
+		typeof (err as CommandError).message === 'string'
+	);
+}